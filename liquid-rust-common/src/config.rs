@@ -2,7 +2,7 @@
 
 use config::{Config, ConfigError, Environment, Source, Value};
 use itertools::Itertools;
-use std::{collections::HashMap, env};
+use std::{cell::RefCell, collections::HashMap, env};
 
 pub const CMD_PREFIX: &'static str = "-L";
 
@@ -13,6 +13,13 @@ lazy_static::lazy_static! {
         // 1. Set defaults
         settings.set_default("print_debug", false).unwrap();
         settings.set_default("dump_constraint", "").unwrap();
+        settings.set_default("spec_files", "").unwrap();
+        settings.set_default("results_dir", "").unwrap();
+        settings.set_default("codegen", "never").unwrap();
+        settings.set_default("check_only", "").unwrap();
+        settings.set_default("panic_freedom", false).unwrap();
+        settings.set_default("int_model", "overflow").unwrap();
+        settings.set_default("mir_phase", "optimized").unwrap();
 
         // 2. Merge with env variables (prefixed with LR_)
         settings.merge(
@@ -28,14 +35,54 @@ lazy_static::lazy_static! {
     };
 }
 
+thread_local! {
+    /// The settings of the compilation running on this thread, if it was given settings of its
+    /// own, see [with_args].
+    static COMPILATION_SETTINGS: RefCell<Option<Config>> = RefCell::new(None);
+}
+
+/// Whether `arg` is a Liquid Rust setting, e.g., `-Lprint_debug=true`. A bare `-L` is a rustc flag
+/// instead, e.g., `-L dependency=target/debug/deps`.
+pub fn is_setting(arg: &str) -> bool {
+    arg.starts_with(CMD_PREFIX) && arg != CMD_PREFIX
+}
+
+/// Run `f` with the settings in `args` taking precedence over the ones of the environment and the
+/// command line on this thread. This lets compilations with different settings run in the same
+/// process, e.g., tests.
+pub fn with_args<R>(args: &[String], f: impl FnOnce() -> R) -> R {
+    let mut settings = SETTINGS.clone();
+    settings
+        .merge(CommandLine::new().prefix(CMD_PREFIX).args(args))
+        .unwrap();
+
+    let outer = COMPILATION_SETTINGS.with(|current| current.replace(Some(settings)));
+    let result = f();
+    COMPILATION_SETTINGS.with(|current| *current.borrow_mut() = outer);
+    result
+}
+
+/// Get a setting of the compilation running on this thread.
+fn get<T>(f: impl FnOnce(&Config) -> T) -> T {
+    COMPILATION_SETTINGS.with(|current| match &*current.borrow() {
+        Some(settings) => f(settings),
+        None => f(&SETTINGS),
+    })
+}
+
 #[derive(Clone, Debug)]
 pub struct CommandLine {
     prefix: Option<String>,
+    /// The arguments to read the settings from, instead of the ones of the process.
+    args: Option<Vec<String>>,
 }
 
 impl CommandLine {
     pub fn new() -> Self {
-        CommandLine { prefix: None }
+        CommandLine {
+            prefix: None,
+            args: None,
+        }
     }
 
     pub fn prefix(mut self, s: &str) -> Self {
@@ -43,6 +90,11 @@ impl CommandLine {
         self
     }
 
+    pub fn args(mut self, args: &[String]) -> Self {
+        self.args = Some(args.to_vec());
+        self
+    }
+
     fn get_prefix(&self) -> String {
         match self.prefix {
             Some(ref prefix) => prefix.to_owned(),
@@ -60,7 +112,19 @@ impl Source for CommandLine {
         let mut res = HashMap::new();
         let uri = "command-line".to_owned();
 
-        for arg in env::args() {
+        let mut args = match &self.args {
+            Some(args) => args.clone(),
+            None => env::args().collect(),
+        }
+        .into_iter();
+        while let Some(arg) = args.next() {
+            // A bare prefix is not a setting but a rustc flag followed by its value, e.g.,
+            // `-L dependency=target/debug/deps` when the prefix is `-L`.
+            if arg == self.get_prefix() {
+                args.next();
+                continue;
+            }
+
             if arg.starts_with(&self.get_prefix()) {
                 let (key, val) = arg
                     .get(self.get_prefix().len()..)
@@ -83,13 +147,133 @@ impl Source for CommandLine {
 }
 
 pub fn print_debug() -> bool {
-    SETTINGS.get_bool("print_debug").unwrap()
+    get(|settings| settings.get_bool("print_debug")).unwrap()
 }
 
 pub fn should_dump_constraint() -> bool {
-    !SETTINGS.get_str("dump_constraint").unwrap().is_empty()
+    !get(|settings| settings.get_str("dump_constraint"))
+        .unwrap()
+        .is_empty()
 }
 
 pub fn dump_constraint() -> String {
-    SETTINGS.get_str("dump_constraint").unwrap()
+    get(|settings| settings.get_str("dump_constraint")).unwrap()
+}
+
+/// Paths of the spec files with specifications for functions of other crates, separated by commas.
+pub fn spec_files() -> Vec<String> {
+    get(|settings| settings.get_str("spec_files"))
+        .unwrap()
+        .split(',')
+        .filter(|path| !path.is_empty())
+        .map(|path| path.to_owned())
+        .collect()
+}
+
+/// Directory where the verification results of each crate are written, if any. This is used by
+/// `cargo liquid` to summarize the results of a whole workspace.
+pub fn results_dir() -> Option<String> {
+    let results_dir = get(|settings| settings.get_str("results_dir")).unwrap();
+    if results_dir.is_empty() {
+        None
+    } else {
+        Some(results_dir)
+    }
+}
+
+/// When compilation continues to code generation after verification.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Codegen {
+    /// Stop after verification. This is the default.
+    Never,
+    /// Continue only if verification succeeds.
+    OnSuccess,
+    /// Always continue. Verification errors are reported as warnings.
+    Always,
+}
+
+pub fn codegen() -> Codegen {
+    match get(|settings| settings.get_str("codegen"))
+        .unwrap()
+        .as_str()
+    {
+        "never" => Codegen::Never,
+        "on_success" => Codegen::OnSuccess,
+        "always" => Codegen::Always,
+        codegen => panic!(
+            "invalid value for `codegen`: `{}` (expected `never`, `on_success` or `always`)",
+            codegen
+        ),
+    }
+}
+
+/// Glob selecting the functions to check by their path, e.g., `my_mod::*`. A `*` matches any
+/// sequence of characters and a `?` matches a single character. Every function is checked if
+/// there is none.
+pub fn check_only() -> Option<String> {
+    let check_only = get(|settings| settings.get_str("check_only")).unwrap();
+    if check_only.is_empty() {
+        None
+    } else {
+        Some(check_only)
+    }
+}
+
+/// Whether to prove that the runtime checks inserted by rustc, e.g., overflow checks, never fail.
+pub fn panic_freedom() -> bool {
+    get(|settings| settings.get_bool("panic_freedom")).unwrap()
+}
+
+/// The semantics assumed for arithmetic operations on machine integers that overflow.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IntModel {
+    /// Overflow is an error, so the result of an operation is its mathematical value. Operations
+    /// checked by rustc panic on overflow, e.g., in debug builds, and the others must be proved
+    /// not to overflow. This is the default.
+    Overflow,
+    /// The result wraps around the range of the integer type, as in builds without overflow
+    /// checks.
+    Wrapping,
+}
+
+pub fn int_model() -> IntModel {
+    match get(|settings| settings.get_str("int_model"))
+        .unwrap()
+        .as_str()
+    {
+        "overflow" => IntModel::Overflow,
+        "wrapping" => IntModel::Wrapping,
+        int_model => panic!(
+            "invalid value for `int_model`: `{}` (expected `overflow` or `wrapping`)",
+            int_model
+        ),
+    }
+}
+
+/// The MIR of a function that is verified.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MirPhase {
+    /// The MIR after optimizations, compiled with `-O` unless code is generated. This is the
+    /// default.
+    Optimized,
+    /// The MIR checked by the borrow checker. Optimizations never run on it, so it doesn't depend
+    /// on the optimization level or on the optimizations of a particular rustc version.
+    Promoted,
+    /// The MIR right after it is built from the source, before any transformation.
+    Built,
+}
+
+pub fn mir_phase() -> MirPhase {
+    match get(|settings| settings.get_str("mir_phase"))
+        .unwrap()
+        .as_str()
+    {
+        "optimized" => MirPhase::Optimized,
+        "promoted" => MirPhase::Promoted,
+        "built" => MirPhase::Built,
+        mir_phase => panic!(
+            "invalid value for `mir_phase`: `{}` (expected `optimized`, `promoted` or `built`)",
+            mir_phase
+        ),
+    }
 }
@@ -6,3 +6,4 @@ extern crate rustc_index;
 pub mod config;
 pub mod index;
 pub mod ordered_map;
+pub mod results;
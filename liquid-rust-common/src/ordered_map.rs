@@ -1,7 +1,7 @@
 use std::iter::FromIterator;
 
 /// An associative array that preserves insertion order.
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct OrderedMap<K, V> {
     inner: Vec<(K, V)>,
 }
@@ -0,0 +1,54 @@
+//! Verification results of a crate, written by the driver and summarized by `cargo liquid`.
+
+use std::{fs, io, path::Path};
+
+/// The verification results of a single crate.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CrateResults {
+    /// Number of functions whose body was checked.
+    pub checked: usize,
+    /// Number of functions that failed verification, either their body or, for trait method
+    /// implementations, their signature.
+    pub failed: usize,
+    /// Number of errors reported for the crate, including invalid annotations that don't make any
+    /// check fail.
+    pub errors: usize,
+    /// Functions whose body was not checked, together with the reason, e.g., `foo (trusted)`.
+    pub skipped: Vec<String>,
+}
+
+impl CrateResults {
+    /// Whether every checked function was verified and no error was reported.
+    pub fn is_ok(&self) -> bool {
+        self.failed == 0 && self.errors == 0
+    }
+
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let mut contents = format!(
+            "checked {}\nfailed {}\nerrors {}\n",
+            self.checked, self.failed, self.errors
+        );
+        for skipped in &self.skipped {
+            contents.push_str(&format!("skipped {}\n", skipped));
+        }
+        fs::write(path, contents)
+    }
+
+    pub fn read(path: &Path) -> io::Result<Self> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "invalid results file");
+        let mut results = CrateResults::default();
+
+        for line in fs::read_to_string(path)?.lines() {
+            let (key, value) = line.split_once(' ').ok_or_else(invalid)?;
+            match key {
+                "checked" => results.checked = value.parse().map_err(|_| invalid())?,
+                "failed" => results.failed = value.parse().map_err(|_| invalid())?,
+                "errors" => results.errors = value.parse().map_err(|_| invalid())?,
+                "skipped" => results.skipped.push(value.to_owned()),
+                _ => return Err(invalid()),
+            }
+        }
+
+        Ok(results)
+    }
+}
@@ -13,16 +13,26 @@
 //! This module defines utilities for interacting with nll facts,
 //! collected during Rust compilation.
 
+use crate::lower::{Lower, LowerCtx, LowerError};
+
 use csv::ReaderBuilder;
-use hashconsing::{HConsign, HashConsign};
 use lazy_static::lazy_static;
-use liquid_rust_common::index::newtype_index;
-use polonius_engine;
+use liquid_rust_common::index::{newtype_index, IndexVec};
+use liquid_rust_lrir::{
+    mir::{self as lrir, PlaceElem},
+    ty::{self, FnSig, TyKind},
+};
+use polonius_engine::{self, Algorithm, Output};
 use regex::Regex;
+use rustc_hir::def_id::DefId;
 use rustc_index::vec::Idx;
-use rustc_middle::mir;
+use rustc_middle::{mir, ty::TyCtxt};
 use serde::de::{Deserialize, DeserializeOwned, Deserializer};
-use std::{path::Path, str::FromStr};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
 /// Macro for declaring index types for referencing interned facts.
 macro_rules! index_type {
@@ -60,15 +70,21 @@ index_type!(Place, X);
 /// When we load facts out of the table, they are essentially random
 /// strings. We create an intern table to map those to integers.
 pub struct Interner {
-    pub points: HConsign<Point>,
+    points: HashMap<Point, PointIndex>,
 }
 
 impl Interner {
     pub fn new() -> Self {
         Self {
-            points: HConsign::empty(),
+            points: HashMap::new(),
         }
     }
+
+    /// The index of `point`, which is interned if it wasn't already.
+    pub fn point_index(&mut self, point: Point) -> PointIndex {
+        let fresh = PointIndex::new(self.points.len());
+        *self.points.entry(point).or_insert(fresh)
+    }
 }
 
 pub trait InternTo<To> {
@@ -218,8 +234,7 @@ newtype_index! {
 
 impl InternTo<PointIndex> for String {
     fn intern(interner: &mut Interner, input: Self) -> PointIndex {
-        let p = input.parse().unwrap();
-        PointIndex::new(interner.points.mk(p).uid() as usize)
+        interner.point_index(input.parse().unwrap())
     }
 }
 
@@ -249,7 +264,6 @@ impl FromStr for Region {
         lazy_static! {
             static ref RE: Regex = Regex::new(r"^'_#(?P<id>\d+)r$").unwrap();
         }
-        println!("{}", region);
         let caps = RE.captures(region).unwrap();
         let id: usize = caps["id"].parse().unwrap();
         Ok(Self::new(id))
@@ -363,3 +377,155 @@ pub fn load_facts(facts_dir: &Path) -> FactLoader {
     fl.load_all_facts(facts_dir);
     fl
 }
+
+/// The directory where rustc writes the borrow checker facts of the function `def_id`.
+pub(crate) fn facts_dir(tcx: TyCtxt, def_id: DefId) -> PathBuf {
+    Path::new(&tcx.sess.opts.debugging_opts.nll_facts_dir)
+        .join(tcx.def_path(def_id).to_filename_friendly_no_crate())
+}
+
+/// The places the references in each local of `lcx.body` may point to at the start of every basic
+/// block, according to the facts in `facts_dir`. The references in the arguments point to memory
+/// owned by a caller, so they keep their region in `fn_sig`, and so does every local they flow
+/// into.
+pub(crate) fn infer_regions(
+    lcx: LowerCtx,
+    fn_sig: &FnSig,
+    facts_dir: &Path,
+) -> Result<IndexVec<mir::BasicBlock, HashMap<mir::Local, ty::Region>>, LowerError> {
+    let body = lcx.body;
+    let FactLoader {
+        mut interner,
+        facts,
+    } = load_facts(facts_dir);
+    let output = Output::compute(&facts, Algorithm::Naive, true);
+
+    // A loan is created in the middle of the assignment of a borrow.
+    let mut borrowed_places = HashMap::new();
+    for (block, basic_block_data) in body.basic_blocks().iter_enumerated() {
+        for (statement_index, statement) in basic_block_data.statements.iter().enumerate() {
+            if let mir::StatementKind::Assign(box (_, mir::Rvalue::Ref(_, _, place))) =
+                &statement.kind
+            {
+                let point = interner.point_index(Point {
+                    location: mir::Location {
+                        block,
+                        statement_index,
+                    },
+                    typ: PointType::Mid,
+                });
+                let place = place.lower(lcx.with_span(statement.source_info.span))?;
+                borrowed_places.insert(point, place);
+            }
+        }
+    }
+    let loan_places = facts
+        .borrow_region
+        .iter()
+        .map(|(_, loan, point)| (*loan, borrowed_places[point].clone()))
+        .collect::<HashMap<_, _>>();
+
+    let mut origins = HashMap::<_, Vec<_>>::new();
+    for (variable, origin) in &facts.use_of_var_derefs_origin {
+        let local = mir::Local::new(usize::from(*variable));
+        origins.entry(local).or_default().push(*origin);
+    }
+
+    let start = interner.point_index(Point {
+        location: mir::Location::START,
+        typ: PointType::Start,
+    });
+    let subset_at = |point, origin1, origin2| {
+        origin1 == origin2
+            || output
+                .subset
+                .get(&point)
+                .and_then(|subset| subset.get(&origin1))
+                .map_or(false, |origins| origins.contains(&origin2))
+    };
+    // The universal region equal to the origin of each reference argument, which is the one that
+    // flows into the locals the argument is copied or moved to.
+    let mut arg_regions = HashMap::new();
+    let mut universal_regions = vec![];
+    for (local, gv) in body.args_iter().zip(&fn_sig.inputs) {
+        if let TyKind::Ref(_, region, _) = fn_sig.requires[gv].kind() {
+            arg_regions.insert(local, region.clone());
+            for origin in origins.get(&local).into_iter().flatten() {
+                for universal in &facts.universal_region {
+                    if subset_at(start, *universal, *origin)
+                        && subset_at(start, *origin, *universal)
+                    {
+                        universal_regions.push((*universal, local, region.clone()));
+                    }
+                }
+            }
+        }
+    }
+
+    let regions = body
+        .basic_blocks()
+        .indices()
+        .map(|block| {
+            let point = interner.point_index(Point {
+                location: block.start_location(),
+                typ: PointType::Start,
+            });
+            let mut regions = arg_regions.clone();
+            for (local, origins) in &origins {
+                if body.local_kind(*local) == mir::LocalKind::Arg {
+                    continue;
+                }
+                let mut places = vec![];
+                let mut callers = vec![];
+                for origin in origins {
+                    let loans = output
+                        .origin_contains_loan_at
+                        .get(&point)
+                        .and_then(|loans| loans.get(origin));
+                    // Only the loans of the borrows in the body have a place. The loans of a
+                    // reborrow through a local also flow into the origin, so only the reborrows
+                    // through arguments are needed.
+                    let loan_places = loans
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|loan| loan_places.get(loan))
+                        .filter(|place| {
+                            place.projection.first() != Some(&PlaceElem::Deref)
+                                || body.local_kind(place.local) == mir::LocalKind::Arg
+                        });
+                    for place in loan_places {
+                        if !places.contains(place) {
+                            places.push(place.clone());
+                        }
+                    }
+                    for (universal, arg, region) in &universal_regions {
+                        if subset_at(point, *universal, *origin) {
+                            callers.push((*arg, region));
+                        }
+                    }
+                }
+                // A reference that may point to memory owned by a caller or to somewhere else
+                // refers to the memory of the caller through the argument it came from.
+                let region = match (places.is_empty(), callers.as_slice()) {
+                    (true, [(_, region)]) => (*region).clone(),
+                    _ => {
+                        for (arg, _) in callers {
+                            let place = lrir::Place {
+                                local: arg,
+                                projection: vec![PlaceElem::Deref],
+                            };
+                            if !places.contains(&place) {
+                                places.push(place);
+                            }
+                        }
+                        ty::Region::Concrete(places)
+                    }
+                };
+                regions.insert(*local, region);
+            }
+            regions
+        })
+        .collect();
+
+    Ok(regions)
+}
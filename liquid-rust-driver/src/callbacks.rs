@@ -1,46 +1,228 @@
-use crate::{collector::Collector, lower::LowerCtx};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::{
+    borrowck,
+    collector::Collector,
+    extern_specs,
+    lower::{LowerCtx, LowerError},
+};
 
+use liquid_rust_common::{
+    config::{self, Codegen, MirPhase},
+    results::CrateResults,
+};
 use liquid_rust_lrir::ty;
 use liquid_rust_typeck::{global_env::GlobalEnv, Checker, CheckingTask};
 use rustc_driver::{Callbacks, Compilation};
-use rustc_errors::{Diagnostic, Handler};
-use rustc_interface::{interface::Compiler, Queries};
-use rustc_middle::ty::ParamEnv;
+use rustc_errors::{Diagnostic, Handler, Level};
+use rustc_hir::def_id::{DefId, LocalDefId, LOCAL_CRATE};
+use rustc_interface::{
+    interface::{Compiler, Config},
+    Queries, DEFAULT_QUERY_PROVIDERS,
+};
+use rustc_middle::{
+    mir::{Body, BorrowKind, Mutability, Operand, Rvalue, Statement, StatementKind},
+    ty::{query::Providers, ParamEnv, TyCtxt, TyKind, WithOptConstParam},
+};
 use rustc_mir::dataflow::{
     impls::MaybeUninitializedPlaces, move_paths::MoveData, Analysis, MoveDataParamEnv,
 };
+use rustc_session::{DiagnosticOutput, Session};
 
 /// Compiler callbacks for Liquid Rust.
-#[derive(Default)]
-pub(crate) struct LiquidCallbacks;
+pub(crate) struct LiquidCallbacks {
+    /// Whether verification failed. Compilation may still succeed if verification errors are
+    /// reported as warnings.
+    pub failed: bool,
+    /// The directory where rustc writes the borrow checker facts of this compilation.
+    facts_dir: Option<PathBuf>,
+    /// The Liquid Rust settings given to this compilation, e.g., `-Lpanic_freedom=true`.
+    settings: Vec<String>,
+    /// Where diagnostics are written instead of stderr, if anywhere.
+    output: Option<Box<dyn Write + Send>>,
+}
 
 impl LiquidCallbacks {
+    pub fn new(settings: Vec<String>, output: Option<Box<dyn Write + Send>>) -> Self {
+        Self {
+            failed: false,
+            facts_dir: None,
+            settings,
+            output,
+        }
+    }
+
+    /// Remove the borrow checker facts written during compilation.
+    pub fn remove_facts(&self) {
+        if let Some(facts_dir) = &self.facts_dir {
+            // The directory doesn't exist if compilation stopped before borrow checking.
+            let _ = std::fs::remove_dir_all(facts_dir);
+        }
+    }
+
+    /// Write the verification results of the crate to `results_dir`, in a file named after the
+    /// crate and its type, e.g., `my_crate.rlib`.
+    fn write_results(tcx: TyCtxt, results: &CrateResults, results_dir: &Path, handler: &Handler) {
+        let crate_type = tcx.sess.crate_types()[0];
+        let file = format!("{}.{}", tcx.crate_name(LOCAL_CRATE), crate_type);
+
+        if let Err(err) = results.write(&results_dir.join(file)) {
+            handler.warn(&format!("Cannot write verification results: {}", err));
+        }
+    }
+
     fn emit_diagnostics(mut diagnostics: Vec<Diagnostic>, handler: &Handler) {
-        for diagnostic in diagnostics.drain(..) {
+        // Errors would prevent code generation, which always happens in this mode.
+        let downgrade_errors = config::codegen() == Codegen::Always;
+
+        for mut diagnostic in diagnostics.drain(..) {
+            if downgrade_errors && diagnostic.is_error() {
+                diagnostic.level = Level::Warning;
+            }
             handler.emit_diagnostic(&diagnostic);
         }
     }
 }
 
 impl Callbacks for LiquidCallbacks {
+    fn config(&mut self, config: &mut Config) {
+        // rustc only hands the borrow checker facts out through files: they are built and dropped
+        // inside the `mir_borrowck` query, whose `BorrowCheckResult` doesn't carry them, unless
+        // `-Znll-facts` dumps them. They are written to a directory of their own, so that
+        // compilations running in parallel don't overwrite each other's facts and nothing is left
+        // in the working directory.
+        static COMPILATIONS: AtomicUsize = AtomicUsize::new(0);
+        let facts_dir = std::env::temp_dir().join(format!(
+            "liquid-rust-nll-facts-{}-{}",
+            std::process::id(),
+            COMPILATIONS.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        config.opts.debugging_opts.nll_facts = true;
+        config.opts.debugging_opts.nll_facts_dir = facts_dir.to_string_lossy().into_owned();
+        self.facts_dir = Some(facts_dir);
+
+        KEPT_MIR.with(|kept| kept.borrow_mut().clear());
+        config.override_queries = match config::with_args(&self.settings, config::mir_phase) {
+            MirPhase::Optimized => None,
+            MirPhase::Promoted => Some(keep_promoted_mir),
+            MirPhase::Built => Some(keep_built_mir),
+        };
+
+        if let Some(output) = self.output.take() {
+            config.diagnostic_output = DiagnosticOutput::Raw(output);
+        }
+    }
+
     fn after_analysis<'tcx>(
         &mut self,
         compiler: &Compiler,
         queries: &'tcx Queries<'tcx>,
+    ) -> Compilation {
+        let settings = self.settings.clone();
+        config::with_args(&settings, || self.check_crate(compiler, queries))
+    }
+}
+
+impl LiquidCallbacks {
+    fn check_crate<'tcx>(
+        &mut self,
+        compiler: &Compiler,
+        queries: &'tcx Queries<'tcx>,
     ) -> Compilation {
         let handler = compiler.session().diagnostic();
         let mut diagnostics = Vec::new();
 
         queries.global_ctxt().unwrap().peek_mut().enter(|tcx| {
             let lr_tcx = ty::TyCtxt::new();
-            let annotations = Collector::collect(&lr_tcx, tcx, handler, &mut diagnostics);
-            let global_env = GlobalEnv::new(tcx, annotations);
+            let specs = Collector::collect(&lr_tcx, tcx, handler, &mut diagnostics);
+            let extern_sigs = extern_specs::load(&lr_tcx, tcx, handler, &mut diagnostics);
+            let global_env = GlobalEnv::new(tcx, specs.fn_sigs, extern_sigs);
+            let mut results = CrateResults::default();
+
+            let check_only = config::check_only();
+            let is_selected = |def_id: DefId| match &check_only {
+                Some(glob) => glob_matches(glob, &tcx.def_path_str(def_id)),
+                None => true,
+            };
+            let mut skipped = specs
+                .ignored
+                .iter()
+                .map(|def_id| (*def_id, "ignored"))
+                .collect::<Vec<_>>();
+
+            // The functions that failed verification. The signature of a trait method
+            // implementation is checked on top of its body, and both count as one function.
+            let mut failed = HashSet::new();
+
+            for (impl_def_id, trait_def_id) in &specs.trait_impls {
+                if !is_selected(*impl_def_id) {
+                    skipped.push((*impl_def_id, "excluded by `check_only`"));
+                    continue;
+                }
 
+                let impl_sig = &global_env.sigs[impl_def_id];
+                let trait_sig = &global_env.sigs[trait_def_id];
+
+                if !Checker::check_fn_sig_subtyping(impl_sig, trait_sig, &lr_tcx).ok {
+                    failed.insert(*impl_def_id);
+                    let mut diagnostic =
+                        handler.struct_span_fatal(tcx.def_span(*impl_def_id), "Liquid type error");
+                    diagnostic.note(&format!(
+                        "this signature does not refine the signature of `{}`",
+                        tcx.def_path_str(*trait_def_id)
+                    ));
+                    diagnostic.buffer(&mut diagnostics);
+                }
+            }
+
+            let mut verified = HashSet::new();
             for (def_id, fn_sig) in &global_env.sigs {
-                let body = tcx.optimized_mir(*def_id);
-                match LowerCtx::lower_body(tcx, body) {
-                    Ok(lrir_body) => {
+                // Required trait methods don't have a body at all.
+                if !tcx.is_mir_available(*def_id) {
+                    continue;
+                }
+
+                // The signature of an assumed or trusted function is trusted, so its body is never
+                // lowered.
+                let skip_reason = if specs.assumed.contains(def_id) {
+                    Some("assumed")
+                } else if specs.trusted.contains(def_id) {
+                    Some("trusted")
+                } else if !is_selected(*def_id) {
+                    Some("excluded by `check_only`")
+                } else {
+                    None
+                };
+                if let Some(skip_reason) = skip_reason {
+                    skipped.push((*def_id, skip_reason));
+                    continue;
+                }
+
+                results.checked += 1;
+                let body = with_reborrowed_args(tcx, mir_body(tcx, *def_id));
+                let lowered = LowerCtx::lower_body(tcx, &lr_tcx, body).and_then(|lrir_body| {
+                    // The promoted MIR is the one checked by the borrow checker, so its locations
+                    // match the ones in the borrow checker facts.
+                    let regions = if config::mir_phase() == MirPhase::Promoted {
+                        let lcx = LowerCtx::new(tcx, &lr_tcx, body);
+                        let facts_dir = borrowck::facts_dir(tcx, *def_id);
+                        Some(borrowck::infer_regions(lcx, fn_sig, &facts_dir)?)
+                    } else {
+                        None
+                    };
+                    Ok((lrir_body, regions))
+                });
+                match lowered {
+                    Ok((lrir_body, regions)) => {
                         let param_env = tcx.param_env(body.source.def_id());
+                        let body = without_set_discriminant(tcx, body);
                         let move_data = MoveData::gather_moves(body, tcx, param_env).unwrap();
                         let mdpe = mk_mpde(
                             MoveData::gather_moves(body, tcx, param_env).unwrap(),
@@ -57,26 +239,254 @@ impl Callbacks for LiquidCallbacks {
                             fn_sig,
                             move_data,
                             flow_uninit,
+                            regions,
                         );
 
-                        if !Checker::check(task, &lr_tcx).ok {
-                            let span = body.span;
-                            handler
-                                .struct_span_fatal(span, "Liquid type error")
-                                .buffer(&mut diagnostics);
+                        let result = Checker::check(task, &lr_tcx);
+                        if result.ok {
+                            verified.insert(*def_id);
+                        } else {
+                            failed.insert(*def_id);
+                            for (span, kind) in &result.failed_asserts {
+                                handler
+                                    .struct_span_fatal(*span, kind.description())
+                                    .buffer(&mut diagnostics);
+                            }
+                            if result.failed_asserts.is_empty() {
+                                let span = body.span;
+                                handler
+                                    .struct_span_fatal(span, "Liquid type error")
+                                    .buffer(&mut diagnostics);
+                            }
                         }
                     }
-                    Err(_) => {
-                        todo!()
+                    Err(LowerError { kind, span }) => {
+                        // The function is skipped, but the rest of the crate is still checked.
+                        failed.insert(*def_id);
+                        handler
+                            .struct_span_fatal(
+                                span,
+                                &format!("liquid-rust does not support {} yet", kind),
+                            )
+                            .buffer(&mut diagnostics);
                     }
                 };
             }
 
+            results.failed = failed.len();
+
+            // Make sure nothing is silently left unverified. A trait method implementation excluded
+            // by `check_only` is skipped by both loops above.
+            let mut seen = HashSet::new();
+            let mut skipped = skipped
+                .into_iter()
+                .filter(|(def_id, _)| seen.insert(*def_id))
+                .map(|(def_id, reason)| format!("`{}` ({})", tcx.def_path_str(def_id), reason))
+                .collect::<Vec<_>>();
+            skipped.sort();
+            for skipped in &skipped {
+                handler
+                    .struct_note_without_error(&format!("skipped {}", skipped))
+                    .buffer(&mut diagnostics);
+            }
+            results.skipped = skipped;
+
+            // Only the signatures of verified functions are exported. The ones of skipped functions
+            // are only trusted by this crate.
+            if !diagnostics.iter().any(Diagnostic::is_error) {
+                let sources = specs
+                    .sources
+                    .iter()
+                    .filter(|(def_id, _)| verified.contains(*def_id))
+                    .map(|(def_id, source)| (*def_id, source.clone()))
+                    .collect();
+                extern_specs::export(tcx, &global_env, &sources, handler, &mut diagnostics);
+            }
+
+            results.errors = diagnostics.iter().filter(|d| d.is_error()).count();
+            if let Some(results_dir) = config::results_dir() {
+                Self::write_results(tcx, &results, Path::new(&results_dir), handler);
+            }
+
+            self.failed = diagnostics.iter().any(Diagnostic::is_error);
             Self::emit_diagnostics(diagnostics, handler);
         });
+        KEPT_MIR.with(|kept| kept.borrow_mut().clear());
+
+        match config::codegen() {
+            Codegen::OnSuccess if !self.failed => Compilation::Continue,
+            Codegen::Always => Compilation::Continue,
+            _ => Compilation::Stop,
+        }
+    }
+}
+
+/// Whether `text` matches `glob`, where a `*` matches any sequence of characters and a `?` matches
+/// a single character.
+fn glob_matches(glob: &str, text: &str) -> bool {
+    let glob = glob.chars().collect::<Vec<_>>();
+    let text = text.chars().collect::<Vec<_>>();
+
+    let (mut g, mut t) = (0, 0);
+    // The position after the last `*` in `glob` and the position in `text` it is matched up to.
+    let mut backtrack = None;
+
+    while t < text.len() {
+        match glob.get(g) {
+            Some('*') => {
+                g += 1;
+                backtrack = Some((g, t));
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                g += 1;
+                t += 1;
+            }
+            // Let the last `*` match one more character.
+            _ => match backtrack {
+                Some((star_g, star_t)) => {
+                    g = star_g;
+                    t = star_t + 1;
+                    backtrack = Some((star_g, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    glob[g..].iter().all(|&c| c == '*')
+}
+
+thread_local! {
+    /// The bodies kept by [keep_built_mir] or [keep_promoted_mir], with their lifetime erased. They
+    /// are only valid during the compilation that built them, which clears them when it starts and
+    /// once it is checked.
+    static KEPT_MIR: RefCell<HashMap<LocalDefId, Body<'static>>> = RefCell::new(HashMap::new());
+}
+
+/// Keep a copy of the built MIR of every local function, which is stolen by the later phases
+/// before verification.
+fn keep_built_mir(_session: &Session, providers: &mut Providers, _extern: &mut Providers) {
+    providers.mir_built = |tcx, def| {
+        let body = (DEFAULT_QUERY_PROVIDERS.mir_built)(tcx, def);
+        keep_mir(def, &body.borrow());
+        body
+    };
+}
+
+/// Keep a copy of the promoted MIR of every local function, which is stolen by const checking or
+/// optimizations.
+fn keep_promoted_mir(_session: &Session, providers: &mut Providers, _extern: &mut Providers) {
+    providers.mir_promoted = |tcx, def| {
+        let (body, promoted) = (DEFAULT_QUERY_PROVIDERS.mir_promoted)(tcx, def);
+        keep_mir(def, &body.borrow());
+        (body, promoted)
+    };
+}
 
-        Compilation::Stop
+fn keep_mir(def: WithOptConstParam<LocalDefId>, body: &Body) {
+    // The MIR of a const argument is computed separately, and it isn't verified.
+    if def.const_param_did.is_none() {
+        // SAFETY: The body is only used during this compilation, see `KEPT_MIR`.
+        let body = unsafe { std::mem::transmute::<Body, Body<'static>>(body.clone()) };
+        KEPT_MIR.with(|kept| kept.borrow_mut().insert(def.did, body));
+    }
+}
+
+/// The MIR of the local function `def_id` in the phase selected by the `mir_phase` setting.
+fn mir_body(tcx: TyCtxt, def_id: DefId) -> &Body {
+    let local_def_id = def_id.expect_local();
+    let def = WithOptConstParam::unknown(local_def_id);
+
+    // The MIR of the other phases is kept when it is computed, which only happens here if nothing
+    // needed it before.
+    match config::mir_phase() {
+        MirPhase::Optimized => return tcx.optimized_mir(def_id),
+        MirPhase::Promoted => {
+            tcx.ensure().mir_promoted(def);
+        }
+        MirPhase::Built => {
+            tcx.ensure().mir_built(def);
+        }
+    }
+    let body = KEPT_MIR.with(|kept| kept.borrow_mut().remove(&local_def_id));
+    let body = body.unwrap_or_else(|| unreachable!("the MIR of {:?} wasn't kept", def_id));
+    // SAFETY: The body was kept during this compilation, see `KEPT_MIR`.
+    tcx.arena
+        .alloc(unsafe { std::mem::transmute::<Body<'static>, Body>(body) })
+}
+
+/// `body` with every move or copy of a reference argument `x` replaced by the reborrow `&*x`, which
+/// rustc doesn't insert when `x` is assigned to a local. The reference then points to the caller's
+/// memory through the place `*x`, so it can be joined with references to local memory, and `x`
+/// stays initialized for the return. The reborrow replaces the statement in place, so the locations
+/// still match the ones in the borrow checker facts.
+fn with_reborrowed_args<'tcx>(tcx: TyCtxt<'tcx>, body: &'tcx Body<'tcx>) -> &'tcx Body<'tcx> {
+    let reborrow = |statement: &Statement<'tcx>| match &statement.kind {
+        StatementKind::Assign(box (_, Rvalue::Use(Operand::Copy(arg) | Operand::Move(arg))))
+            if arg.projection.is_empty() && body.args_iter().any(|local| local == arg.local) =>
+        {
+            match body.local_decls[arg.local].ty.kind() {
+                TyKind::Ref(region, _, mutbl) => {
+                    let bk = if *mutbl == Mutability::Mut {
+                        BorrowKind::Mut {
+                            allow_two_phase_borrow: false,
+                        }
+                    } else {
+                        BorrowKind::Shared
+                    };
+                    Some(Rvalue::Ref(region, bk, tcx.mk_place_deref(*arg)))
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    };
+    if !body
+        .basic_blocks()
+        .iter()
+        .any(|bb_data| bb_data.statements.iter().any(|st| reborrow(st).is_some()))
+    {
+        return body;
+    }
+
+    let mut new_body = body.clone();
+    for bb_data in new_body.basic_blocks_mut() {
+        for statement in &mut bb_data.statements {
+            if let Some(rvalue) = reborrow(statement) {
+                if let StatementKind::Assign(box (_, old_rvalue)) = &mut statement.kind {
+                    *old_rvalue = rvalue;
+                }
+            }
+        }
+    }
+    tcx.arena.alloc(new_body)
+}
+
+/// `body` with every `discriminant(p) = ..` statement replaced by an assignment initializing `p`,
+/// for computing its move paths. These statements only exist in optimized MIR, and the move paths
+/// of the borrow checker don't support them. The enum is initialized once its discriminant is set.
+fn without_set_discriminant<'tcx>(tcx: TyCtxt<'tcx>, body: &'tcx Body<'tcx>) -> &'tcx Body<'tcx> {
+    let is_set_discriminant =
+        |statement: &Statement| matches!(statement.kind, StatementKind::SetDiscriminant { .. });
+    if !body
+        .basic_blocks()
+        .iter()
+        .any(|bb_data| bb_data.statements.iter().any(is_set_discriminant))
+    {
+        return body;
+    }
+
+    let mut body = body.clone();
+    for bb_data in body.basic_blocks_mut() {
+        for statement in &mut bb_data.statements {
+            if let StatementKind::SetDiscriminant { place, .. } = &statement.kind {
+                let place = **place;
+                let rvalue = Rvalue::Use(Operand::Copy(place));
+                statement.kind = StatementKind::Assign(Box::new((place, rvalue)));
+            }
+        }
     }
+    tcx.arena.alloc(body)
 }
 
 fn mk_mpde<'tcx>(move_data: MoveData<'tcx>, param_env: ParamEnv<'tcx>) -> MoveDataParamEnv<'tcx> {
@@ -1,25 +1,55 @@
-use std::collections::HashMap;
+use std::collections::{hash_map::Entry, HashMap, HashSet};
 
-use liquid_rust_lrir::ty;
-use liquid_rust_parser::{parse_fn_decl, ParseErrorKind};
+use liquid_rust_lrir::ty::{self, refiner::Refiner};
+use liquid_rust_parser::{parse_fn_decl, parse_ty, ParseError, ParseErrorKind};
 
 use rustc_ast::{AttrKind, Attribute, MacArgs};
 use rustc_ast_pretty::pprust::tts_to_string;
 use rustc_errors::{Diagnostic, Handler};
 use rustc_hir::{
-    def_id::DefId, itemlikevisit::ItemLikeVisitor, ForeignItem, ImplItem, Item, ItemKind, TraitItem,
+    def_id::DefId, itemlikevisit::ItemLikeVisitor, ForeignItem, HirId, ImplItem, ImplItemKind,
+    Item, ItemKind, TraitItem, TraitItemKind,
+};
+use rustc_middle::ty::{AssocKind, DefIdTree, TyCtxt, TyKind, VariantDiscr};
+use rustc_span::{
+    symbol::{sym, Ident},
+    BytePos, Pos, Span,
 };
-use rustc_middle::ty::TyCtxt;
-use rustc_span::{BytePos, Pos, Span};
 
 use crate::resolution::Resolver;
 
+/// Specifications collected from the `liquid` annotations of a crate.
+pub(crate) struct Specs {
+    /// The refined signature of every annotated function.
+    pub fn_sigs: HashMap<DefId, ty::FnSig>,
+    /// Functions annotated with `#[liquid::assume]`. Callers use their signature but their bodies
+    /// are never checked.
+    pub assumed: HashSet<DefId>,
+    /// Functions annotated with `#[liquid::trusted]`. Like assumed functions, callers use their
+    /// signature but their bodies are never checked.
+    pub trusted: HashSet<DefId>,
+    /// Functions annotated with `#[liquid::ignore]`. Their signature is discarded, so callers use
+    /// a default one, and their bodies are never checked.
+    pub ignored: HashSet<DefId>,
+    /// Pairs of an annotated trait method implementation and the annotated trait method it
+    /// implements. The signature of the former must refine the signature of the latter.
+    pub trait_impls: Vec<(DefId, DefId)>,
+    /// The source of the annotation of every annotated function, used to export the signatures
+    /// of the crate.
+    pub sources: HashMap<DefId, String>,
+}
+
 pub(crate) struct Collector<'tcx, 'a> {
     lr_tcx: &'a ty::TyCtxt,
     tcx: TyCtxt<'tcx>,
     handler: &'a Handler,
     diagnostics: &'a mut Vec<Diagnostic>,
     annotations: HashMap<DefId, ty::FnSig>,
+    sources: HashMap<DefId, String>,
+    assumed: HashSet<DefId>,
+    trusted: HashSet<DefId>,
+    ignored: HashSet<DefId>,
+    impl_items: Vec<DefId>,
 }
 
 impl<'tcx, 'a> Collector<'tcx, 'a> {
@@ -35,6 +65,11 @@ impl<'tcx, 'a> Collector<'tcx, 'a> {
             handler,
             diagnostics,
             annotations: HashMap::new(),
+            sources: HashMap::new(),
+            assumed: HashSet::new(),
+            trusted: HashSet::new(),
+            ignored: HashSet::new(),
+            impl_items: Vec::new(),
         }
     }
 
@@ -61,14 +96,17 @@ impl<'tcx, 'a> Collector<'tcx, 'a> {
                     _ => continue,
                 };
 
-                // Emit a warning if we already have an annotation for this item.
-                if parsed_annotation {
-                    self.push_warning("Ignoring duplicated annotation.", attr_item.span());
-                }
-
                 match segments {
                     [second] => match &*second.ident.as_str() {
-                        "ty" => {
+                        name @ ("ty" | "assume") => {
+                            // Emit a warning if we already have an annotation for this item.
+                            if parsed_annotation {
+                                self.push_warning(
+                                    "Ignoring duplicated annotation.",
+                                    attr_item.span(),
+                                );
+                            }
+
                             if let MacArgs::Delimited(span, _, tokens) = &attr_item.args {
                                 // FIXME: Is it possible to avoid this allocation?
                                 let input = tts_to_string(tokens);
@@ -76,11 +114,26 @@ impl<'tcx, 'a> Collector<'tcx, 'a> {
 
                                 self.parse_ty_annotation(def_id, &input, input_span);
 
+                                if name == "assume" {
+                                    self.assumed.insert(def_id);
+                                }
+
                                 parsed_annotation = true;
                             } else {
                                 self.push_error("Invalid liquid annotation.", attr_item.span())
                             }
                         }
+                        name @ ("trusted" | "ignore") => {
+                            if let MacArgs::Empty = &attr_item.args {
+                                if name == "trusted" {
+                                    self.trusted.insert(def_id);
+                                } else {
+                                    self.ignored.insert(def_id);
+                                }
+                            } else {
+                                self.push_error("Invalid liquid annotation.", attr_item.span())
+                            }
+                        }
                         _ => self.push_error("Invalid liquid annotation.", attr_item.span()),
                     },
                     _ => self.push_error("Invalid liquid annotation.", attr_item.span()),
@@ -90,28 +143,223 @@ impl<'tcx, 'a> Collector<'tcx, 'a> {
     }
 
     fn parse_ty_annotation(&mut self, def_id: DefId, input: &str, input_span: Span) {
-        match parse_fn_decl(&input.trim_matches('"')) {
+        let input = input.trim_matches('"');
+        match parse_fn_decl(input) {
             Ok(fn_decl) => {
                 // FIXME: we probably need to move this to somewhere else once we handle references
                 // as the resolution/lowering will require more information from the compiler.
-                let fn_decl = Resolver::new(self.lr_tcx).resolve(fn_decl);
-                self.annotations.insert(def_id, fn_decl);
+                let module = module_of(self.tcx, def_id);
+                let fn_decl = match Resolver::new(self.lr_tcx, Some(module)).resolve(fn_decl) {
+                    Ok(fn_decl) => fn_decl,
+                    Err(err) => {
+                        self.push_error(err.message(), input_span);
+                        return;
+                    }
+                };
+                // FIXME: late-bound regions are ignored until references are properly supported.
+                let rust_sig = self.tcx.fn_sig(def_id).skip_binder();
+                match fn_decl.with_int_tys(rust_sig, self.lr_tcx) {
+                    Some(fn_decl) => {
+                        // Structs and enums are only known to their crate, so signatures
+                        // mentioning them aren't exported.
+                        let mentions_local_adt = fn_decl
+                            .requires
+                            .iter()
+                            .map(|(_, ty)| ty)
+                            .chain(fn_decl.ensures.iter().map(|(_, ty)| ty))
+                            .any(mentions_local_adt);
+                        if !mentions_local_adt {
+                            self.sources.insert(def_id, input.to_owned());
+                        }
+                        self.annotations.insert(def_id, fn_decl);
+                    }
+                    None => self.push_error(
+                        "Type annotation doesn't match the signature of the function.",
+                        input_span,
+                    ),
+                }
+            }
+            Err(err) => self.push_parse_error(err, input_span),
+        }
+    }
+
+    fn push_parse_error(&mut self, err: ParseError, input_span: Span) {
+        // Turn the relative span of the parsing error into an absolute one.
+        let lo = input_span.lo() + BytePos::from_usize(err.span.start + 2);
+        let hi = input_span.lo() + BytePos::from_usize(err.span.end + 2);
+        let span = Span::new(lo, hi, input_span.ctxt());
+
+        use ParseErrorKind::*;
+        let msg = match err.kind {
+            UnexpectedEOF => "Type annotation ended unexpectedly.",
+            UnexpectedToken(_token) => "Unexpected token.",
+        };
+
+        self.push_error(msg, span);
+    }
+
+    /// Register the struct `def_id` if its values can be refined, i.e., if it isn't generic and
+    /// all its fields are integers or booleans. A field has the type of its
+    /// `#[liquid::field("..")]` annotation, if any, or a trivial one otherwise.
+    fn collect_struct(&mut self, def_id: DefId) {
+        let tcx = self.tcx;
+        let span = tcx.def_span(def_id);
+        let fields = &tcx.adt_def(def_id).non_enum_variant().fields;
+
+        let names = fields
+            .iter()
+            .map(|field| field.ident.to_string())
+            .collect::<Vec<_>>();
+        let annotations = fields
+            .iter()
+            .map(|field| self.field_annotation(field.did))
+            .collect::<Vec<_>>();
+
+        let refinable = tcx.generics_of(def_id).count() == 0
+            && fields.iter().all(|field| {
+                matches!(
+                    tcx.type_of(field.did).kind(),
+                    TyKind::Bool | TyKind::Int(_) | TyKind::Uint(_)
+                )
+            });
+        if !refinable {
+            if annotations.iter().any(Option::is_some) {
+                self.push_error(
+                    "Only non-generic structs with integer and boolean fields can be refined.",
+                    span,
+                );
             }
-            Err(err) => {
-                // Turn the relative span of the parsing error into an absolute one.
-                let lo = input_span.lo() + BytePos::from_usize(err.span.start + 2);
-                let hi = input_span.lo() + BytePos::from_usize(err.span.end + 2);
-                let span = Span::new(lo, hi, input_span.ctxt());
-
-                use ParseErrorKind::*;
-                let msg = match err.kind {
-                    UnexpectedEOF => "Type annotation ended unexpectedly.",
-                    UnexpectedToken(_token) => "Unexpected token.",
+            return;
+        }
+
+        let mut asts = vec![];
+        for (name, annotation) in names.iter().zip(&annotations) {
+            let ast = match annotation {
+                Some((input, input_span)) => match parse_ty(input) {
+                    Ok(ast) => Some(ast),
+                    Err(err) => return self.push_parse_error(err, *input_span),
+                },
+                None => None,
+            };
+            asts.push((name.as_str(), ast));
+        }
+        let tys =
+            match Resolver::new(self.lr_tcx, Some(module_of(tcx, def_id))).resolve_fields(asts) {
+                Ok(tys) => tys,
+                Err(err) => return self.push_error(err.message(), span),
+            };
+
+        let mut field_tys = vec![];
+        for (i, (ty, field)) in tys.iter().zip(fields).enumerate() {
+            let rust_ty = tcx.type_of(field.did);
+            let ty = match ty {
+                Some(ty) => match ty::with_int_tys(ty, rust_ty, self.lr_tcx) {
+                    Some(ty) => ty,
+                    None => {
+                        let (_, input_span) = annotations[i].as_ref().unwrap();
+                        return self.push_error(
+                            "Type annotation doesn't match the type of the field.",
+                            *input_span,
+                        );
+                    }
+                },
+                None => Refiner::trivial(self.lr_tcx, rust_ty),
+            };
+            field_tys.push((ty::Field::from_usize(i), ty));
+        }
+
+        self.lr_tcx.register_adt_def(ty::AdtDefS {
+            def_id,
+            name: tcx.item_name(def_id).to_string(),
+            module: module_of(tcx, def_id),
+            field_names: names,
+            fields: field_tys.into_iter().collect(),
+        });
+    }
+
+    /// Returns the source and span of the `#[liquid::field("..")]` annotation of the field
+    /// `def_id`, if any.
+    fn field_annotation(&mut self, def_id: DefId) -> Option<(String, Span)> {
+        let mut annotation = None;
+
+        for attribute in self.tcx.get_attrs(def_id) {
+            if let AttrKind::Normal(attr_item, ..) = &attribute.kind {
+                let segments = match attr_item.path.segments.as_slice() {
+                    [first, segments @ ..] if first.ident.as_str() == "liquid" => segments,
+                    _ => continue,
                 };
 
-                self.push_error(msg, span);
+                match (segments, &attr_item.args) {
+                    ([second], MacArgs::Delimited(span, _, tokens))
+                        if second.ident.as_str() == "field" =>
+                    {
+                        if annotation.is_some() {
+                            self.push_warning("Ignoring duplicated annotation.", attr_item.span());
+                            continue;
+                        }
+                        let input = tts_to_string(tokens).trim_matches('"').to_owned();
+                        annotation = Some((input, span.entire()));
+                    }
+                    _ => self.push_error("Invalid liquid annotation.", attr_item.span()),
+                }
             }
         }
+
+        annotation
+    }
+
+    /// Parse the annotations of the function-like item with the given `hir_id` and return its
+    /// [DefId].
+    fn collect_fn(&mut self, hir_id: HirId) -> DefId {
+        let def_id = self.tcx.hir().local_def_id(hir_id).to_def_id();
+        let attrs = self.tcx.hir().attrs(hir_id);
+        self.parse_annotations(def_id, attrs);
+        def_id
+    }
+
+    /// Give every unannotated implementation of an annotated trait method the signature of the
+    /// trait method, so its body is checked against the specification of the trait.
+    ///
+    /// Returns the pairs of annotated implementations and the trait methods they implement.
+    fn inherit_trait_sigs(&mut self) -> Vec<(DefId, DefId)> {
+        let mut trait_impls = Vec::new();
+
+        for &impl_item in &self.impl_items {
+            if self.ignored.contains(&impl_item) {
+                continue;
+            }
+
+            let trait_item = match self.trait_item_of(impl_item) {
+                Some(trait_item) => trait_item,
+                None => continue,
+            };
+
+            let trait_sig = match self.annotations.get(&trait_item) {
+                Some(trait_sig) => trait_sig.clone(),
+                None => continue,
+            };
+
+            match self.annotations.entry(impl_item) {
+                Entry::Occupied(_) => trait_impls.push((impl_item, trait_item)),
+                Entry::Vacant(entry) => {
+                    entry.insert(trait_sig);
+                }
+            }
+        }
+
+        trait_impls
+    }
+
+    /// Find the trait method implemented by `impl_item`, if any.
+    fn trait_item_of(&self, impl_item: DefId) -> Option<DefId> {
+        let impl_def_id = self.tcx.impl_of_method(impl_item)?;
+        let trait_def_id = self.tcx.trait_id_of_impl(impl_def_id)?;
+        let ident = Ident::with_dummy_span(self.tcx.item_name(impl_item));
+
+        self.tcx
+            .associated_items(trait_def_id)
+            .find_by_name_and_kind(self.tcx, ident, AssocKind::Fn, trait_def_id)
+            .map(|trait_item| trait_item.def_id)
     }
 
     pub(crate) fn collect(
@@ -119,25 +367,141 @@ impl<'tcx, 'a> Collector<'tcx, 'a> {
         tcx: TyCtxt<'tcx>,
         handler: &'a Handler,
         diagnostics: &'a mut Vec<Diagnostic>,
-    ) -> HashMap<DefId, ty::FnSig> {
+    ) -> Specs {
         let mut collector = Self::new(lr_tcx, tcx, handler, diagnostics);
 
+        // Structs and enums are collected first, as signatures may refer to them.
+        for item in tcx.hir().krate().items.values() {
+            let def_id = tcx.hir().local_def_id(item.hir_id()).to_def_id();
+            match item.kind {
+                ItemKind::Struct(..) => collector.collect_struct(def_id),
+                ItemKind::Enum(..) => {
+                    if let Some(enum_def) = enum_def(tcx, def_id) {
+                        lr_tcx.register_enum_def(enum_def);
+                    }
+                }
+                _ => {}
+            }
+        }
+        for &name in &[sym::option_type, sym::result_type] {
+            if let Some(enum_def) = tcx
+                .get_diagnostic_item(name)
+                .and_then(|def_id| enum_def(tcx, def_id))
+            {
+                lr_tcx.register_enum_def(enum_def);
+            }
+        }
+
         tcx.hir().krate().visit_all_item_likes(&mut collector);
-        collector.annotations
+
+        // The signature of ignored functions is discarded before it can be inherited.
+        for def_id in &collector.ignored {
+            collector.annotations.remove(def_id);
+            collector.sources.remove(def_id);
+        }
+        let trait_impls = collector.inherit_trait_sigs();
+
+        Specs {
+            fn_sigs: collector.annotations,
+            assumed: collector.assumed,
+            trusted: collector.trusted,
+            ignored: collector.ignored,
+            trait_impls,
+            sources: collector.sources,
+        }
     }
 }
 
 impl<'hir, 'tcx, 'a> ItemLikeVisitor<'hir> for Collector<'tcx, 'a> {
     fn visit_item(&mut self, item: &'hir Item<'hir>) {
         if let ItemKind::Fn(..) = item.kind {
-            let hir_id = item.hir_id();
-            let def_id = self.tcx.hir().local_def_id(hir_id).to_def_id();
-            let attrs = self.tcx.hir().attrs(hir_id);
-            self.parse_annotations(def_id, attrs);
+            self.collect_fn(item.hir_id());
+        }
+    }
+
+    fn visit_trait_item(&mut self, trait_item: &'hir TraitItem<'hir>) {
+        if let TraitItemKind::Fn(..) = trait_item.kind {
+            self.collect_fn(trait_item.hir_id());
+        }
+    }
+
+    fn visit_impl_item(&mut self, impl_item: &'hir ImplItem<'hir>) {
+        if let ImplItemKind::Fn(..) = impl_item.kind {
+            let def_id = self.collect_fn(impl_item.hir_id());
+            self.impl_items.push(def_id);
         }
     }
 
-    fn visit_trait_item(&mut self, _trait_item: &'hir TraitItem<'hir>) {}
-    fn visit_impl_item(&mut self, _impl_item: &'hir ImplItem<'hir>) {}
     fn visit_foreign_item(&mut self, _foreign_item: &'hir ForeignItem<'hir>) {}
 }
+
+/// The module defining `def_id`, where the names in its annotations are resolved.
+fn module_of(tcx: TyCtxt, def_id: DefId) -> DefId {
+    match def_id.as_local() {
+        Some(local_def_id) => tcx.parent_module_from_def_id(local_def_id).to_def_id(),
+        None => tcx.parent(def_id).unwrap(),
+    }
+}
+
+/// Whether `ty` mentions a struct or an enum of the crate.
+fn mentions_local_adt(ty: &ty::Ty) -> bool {
+    match ty.kind() {
+        ty::TyKind::Adt(..) => true,
+        ty::TyKind::Enum(enum_def, _, variants) => {
+            enum_def.def_id.is_local() || variants.iter().any(mentions_local_adt)
+        }
+        ty::TyKind::Tuple(tup) => tup.types().any(mentions_local_adt),
+        _ => false,
+    }
+}
+
+/// The definition of the enum `def_id` if its variants can be refined, i.e., if it is an enum of
+/// the crate, `Option` or `Result`, the discriminant of each variant is its index and every field
+/// is of a type parameter of the enum, an integer or a boolean.
+fn enum_def(tcx: TyCtxt, def_id: DefId) -> Option<ty::EnumDefS> {
+    let known = def_id.is_local()
+        || tcx.is_diagnostic_item(sym::option_type, def_id)
+        || tcx.is_diagnostic_item(sym::result_type, def_id);
+    let counts = tcx.generics_of(def_id).own_counts();
+    let adt_def = tcx.adt_def(def_id);
+    if !known || !adt_def.is_enum() || counts.lifetimes > 0 || counts.consts > 0 {
+        return None;
+    }
+
+    let variants = adt_def
+        .variants
+        .iter_enumerated()
+        .map(|(i, variant)| {
+            if variant.discr != VariantDiscr::Relative(i.as_u32()) {
+                return None;
+            }
+            let fields = variant
+                .fields
+                .iter()
+                .map(|field| match tcx.type_of(field.did).kind() {
+                    TyKind::Param(param) => Some(ty::VariantField::Param(param.index as usize)),
+                    TyKind::Bool => Some(ty::VariantField::Base(ty::BaseTy::Bool)),
+                    TyKind::Int(int_ty) => {
+                        Some(ty::VariantField::Base(ty::BaseTy::Int((*int_ty).into())))
+                    }
+                    TyKind::Uint(uint_ty) => {
+                        Some(ty::VariantField::Base(ty::BaseTy::Int((*uint_ty).into())))
+                    }
+                    _ => None,
+                })
+                .collect::<Option<_>>()?;
+            Some(ty::VariantDef {
+                name: variant.ident.to_string(),
+                fields,
+            })
+        })
+        .collect::<Option<_>>()?;
+
+    Some(ty::EnumDefS {
+        def_id,
+        name: tcx.item_name(def_id).to_string(),
+        module: module_of(tcx, def_id),
+        params: counts.types,
+        variants,
+    })
+}
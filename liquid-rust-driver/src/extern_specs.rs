@@ -0,0 +1,214 @@
+//! Specifications for functions of other crates, e.g., `core`, which cannot be annotated.
+//!
+//! Specifications are read from spec files (`.lrspec`). A spec file is a sequence of entries, each
+//! one consisting of the path of a function followed by its refined signature, using the syntax of
+//! `#[liquid::ty]` annotations:
+//!
+//! ```text
+//! // Comments start with `//`.
+//! fn core::cmp::max(a: int, b: int) -> {v: int | v >= a && v >= b}
+//! ```
+//!
+//! An entry spans until the next line starting with `fn`. Inherent methods are prefixed by the type
+//! of their `impl`, e.g., `i32::signum`. The lifetime parameters of a signature follow the path,
+//! e.g., `fn core::convert::identity<'a>(x: &'a int) -> &'a int`.
+//!
+//! When compiling a library to an rlib or rmeta, the signatures of its annotated functions are
+//! exported to a spec file next to it, which is loaded when compiling crates depending on it.
+
+use std::{collections::HashMap, fs, path::PathBuf};
+
+use liquid_rust_common::config::{self, Codegen};
+use liquid_rust_lrir::ty;
+use liquid_rust_parser::{parse_fn_decl, ParseErrorKind};
+use liquid_rust_typeck::global_env::GlobalEnv;
+use rustc_errors::{Diagnostic, Handler};
+use rustc_hir::def_id::{DefId, LOCAL_CRATE};
+use rustc_middle::ty::TyCtxt;
+use rustc_session::{
+    config::{CrateType, OutputType},
+    output::filename_for_input,
+};
+
+use crate::resolution::Resolver;
+
+/// Extension of the spec files exported next to the rlib of a crate.
+const SPEC_EXTENSION: &str = "lrspec";
+
+/// Specifications shipped with Liquid Rust.
+const BUILTIN_SPECS: &[(&str, &str)] = &[("core.lrspec", include_str!("../specs/core.lrspec"))];
+
+/// Load the built-in specifications, the ones exported by the dependencies of the crate and the
+/// ones in the spec files set in the configuration. A specification overrides any previous
+/// specification for the same path, in that order.
+pub(crate) fn load(
+    lr_tcx: &ty::TyCtxt,
+    tcx: TyCtxt,
+    handler: &Handler,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> HashMap<String, ty::FnSig> {
+    let mut loader = Loader {
+        lr_tcx,
+        handler,
+        diagnostics,
+        specs: HashMap::new(),
+    };
+
+    for (file, source) in BUILTIN_SPECS {
+        loader.load_file(file, source);
+    }
+
+    for &cnum in tcx.crates() {
+        let crate_source = tcx.used_crate_source(cnum);
+        let path = match crate_source
+            .rlib
+            .as_ref()
+            .or_else(|| crate_source.rmeta.as_ref())
+        {
+            Some((path, _)) => path.with_extension(SPEC_EXTENSION),
+            None => continue,
+        };
+
+        // Crates not compiled by Liquid Rust don't have a spec file.
+        if let Ok(source) = fs::read_to_string(&path) {
+            loader.load_file(&path.display().to_string(), &source);
+        }
+    }
+
+    for file in config::spec_files() {
+        match fs::read_to_string(&file) {
+            Ok(source) => loader.load_file(&file, &source),
+            Err(err) => loader.push_error(&format!("Cannot read spec file `{}`: {}", file, err)),
+        }
+    }
+
+    loader.specs
+}
+
+/// Export the signatures in `sources` of the functions of a library to a spec file next to its rlib.
+pub(crate) fn export(
+    tcx: TyCtxt,
+    global_env: &GlobalEnv,
+    sources: &HashMap<DefId, String>,
+    handler: &Handler,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    // The spec file is only exported next to an rlib or rmeta that is actually emitted, which needs
+    // compilation to continue after verification.
+    let output_types = &tcx.sess.opts.output_types;
+    let emits_lib = tcx.sess.crate_types().contains(&CrateType::Rlib)
+        && (output_types.contains_key(&OutputType::Exe)
+            || output_types.contains_key(&OutputType::Metadata));
+    if config::codegen() == Codegen::Never || !emits_lib {
+        return;
+    }
+
+    let mut entries = sources
+        .iter()
+        .map(|(def_id, source)| {
+            let sig = source.trim_start()["fn".len()..].trim_start();
+            format!("fn {}{}\n", global_env.spec_path(*def_id), sig)
+        })
+        .collect::<Vec<_>>();
+    entries.sort();
+
+    let path = spec_file_path(tcx);
+    if let Err(err) = fs::write(&path, entries.concat()) {
+        handler
+            .struct_fatal(&format!(
+                "Cannot write spec file `{}`: {}",
+                path.display(),
+                err
+            ))
+            .buffer(diagnostics);
+    }
+}
+
+/// Returns the path of the spec file exported next to the rlib of the current crate.
+fn spec_file_path(tcx: TyCtxt) -> PathBuf {
+    let crate_name = tcx.crate_name(LOCAL_CRATE);
+    let outputs = tcx.output_filenames(LOCAL_CRATE);
+
+    filename_for_input(tcx.sess, CrateType::Rlib, &crate_name.as_str(), &outputs)
+        .with_extension(SPEC_EXTENSION)
+}
+
+struct Loader<'a> {
+    lr_tcx: &'a ty::TyCtxt,
+    handler: &'a Handler,
+    diagnostics: &'a mut Vec<Diagnostic>,
+    specs: HashMap<String, ty::FnSig>,
+}
+
+impl Loader<'_> {
+    fn push_error(&mut self, message: &str) {
+        self.handler.struct_fatal(message).buffer(self.diagnostics);
+    }
+
+    fn load_file(&mut self, file: &str, source: &str) {
+        for (line, entry) in entries(source) {
+            // Split `fn path<'a>(...) -> ...` into the path and the signature `fn<'a>(...) -> ...`.
+            // Only the first entry may not start with `fn`.
+            let split = entry.strip_prefix("fn ").and_then(|entry| {
+                let i = entry.find(|c| c == '<' || c == '(')?;
+                Some((entry[..i].trim(), &entry[i..]))
+            });
+            let (path, sig) = match split {
+                Some(split) => split,
+                None => {
+                    self.push_error(&format!(
+                        "{}:{}: Expected a function signature.",
+                        file, line
+                    ));
+                    continue;
+                }
+            };
+
+            match parse_fn_decl(&format!("fn{}", sig)) {
+                Ok(fn_decl) => match Resolver::new(self.lr_tcx, None).resolve(fn_decl) {
+                    Ok(fn_sig) => {
+                        self.specs.insert(path.to_owned(), fn_sig);
+                    }
+                    Err(err) => self.push_error(&format!("{}:{}: {}", file, line, err.message())),
+                },
+                Err(err) => {
+                    use ParseErrorKind::*;
+                    let msg = match err.kind {
+                        UnexpectedEOF => "Type annotation ended unexpectedly.",
+                        UnexpectedToken(_token) => "Unexpected token.",
+                    };
+
+                    self.push_error(&format!("{}:{}: {}", file, line, msg));
+                }
+            }
+        }
+    }
+}
+
+/// Split the source of a spec file into its entries, together with the line where each one
+/// starts. Comments and blank lines are dropped.
+fn entries(source: &str) -> Vec<(usize, String)> {
+    let mut entries: Vec<(usize, String)> = Vec::new();
+
+    for (i, line) in source.lines().enumerate() {
+        let line = match line.find("//") {
+            Some(comment) => &line[..comment],
+            None => line,
+        }
+        .trim();
+
+        if line.is_empty() {
+            continue;
+        }
+
+        match entries.last_mut() {
+            Some((_, entry)) if !line.starts_with("fn ") => {
+                entry.push(' ');
+                entry.push_str(line);
+            }
+            _ => entries.push((i + 1, line.to_owned())),
+        }
+    }
+
+    entries
+}
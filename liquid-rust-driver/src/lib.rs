@@ -11,22 +11,49 @@ extern crate rustc_index;
 extern crate rustc_interface;
 extern crate rustc_middle;
 extern crate rustc_mir;
+extern crate rustc_mir_build;
 extern crate rustc_serialize;
+extern crate rustc_session;
 extern crate rustc_span;
 
 pub mod borrowck;
 mod callbacks;
 mod collector;
+mod extern_specs;
 mod lower;
 mod resolution;
 
 use callbacks::LiquidCallbacks;
+use liquid_rust_common::config;
 
-use rustc_driver::{catch_with_exit_code, RunCompiler};
+use std::io::Write;
 
-/// Run Liquid Rust and return the exit status code.
+use rustc_driver::{catch_with_exit_code, RunCompiler, EXIT_SUCCESS};
+
+/// The exit status code of a compilation that reported errors, e.g., verification errors, as
+/// opposed to one that crashed.
+pub const EXIT_FAILURE: i32 = rustc_driver::EXIT_FAILURE;
+
+/// Run Liquid Rust and return the exit status code. The exit status code is non-zero if
+/// verification failed, even if compilation continued and succeeded.
+///
+/// Liquid Rust settings in `args`, e.g., `-Lpanic_freedom=true`, apply to this compilation only
+/// and take precedence over the ones of the environment and the command line.
 pub fn run_compiler(args: Vec<String>) -> i32 {
-    let mut callbacks = LiquidCallbacks::default();
+    run_compiler_with_output(args, None)
+}
+
+/// Like [run_compiler], but diagnostics are written to `output`, if any, instead of stderr.
+pub fn run_compiler_with_output(args: Vec<String>, output: Option<Box<dyn Write + Send>>) -> i32 {
+    let (settings, args): (Vec<_>, Vec<_>) =
+        args.into_iter().partition(|arg| config::is_setting(arg));
+    let mut callbacks = LiquidCallbacks::new(settings, output);
 
-    catch_with_exit_code(move || RunCompiler::new(&args, &mut callbacks).run())
+    let exit_code = catch_with_exit_code(|| RunCompiler::new(&args, &mut callbacks).run());
+    callbacks.remove_facts();
+    if exit_code == EXIT_SUCCESS && callbacks.failed {
+        EXIT_FAILURE
+    } else {
+        exit_code
+    }
 }
@@ -7,7 +7,7 @@ use rustc_middle::mir;
 impl<'tcx> Lower<'tcx> for mir::BasicBlockData<'tcx> {
     type Output = BasicBlockData<'tcx>;
 
-    fn lower(&self, lcx: LowerCtx<'tcx>) -> LowerResult<Self::Output> {
+    fn lower(&self, lcx: LowerCtx<'_, 'tcx>) -> LowerResult<Self::Output> {
         let output = BasicBlockData {
             statements: self
                 .statements
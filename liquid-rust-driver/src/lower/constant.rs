@@ -1,4 +1,4 @@
-use crate::lower::{Lower, LowerCtx, LowerResult};
+use crate::lower::{Lower, LowerCtx, LowerErrorKind, LowerResult};
 
 use liquid_rust_lrir::mir::Constant;
 
@@ -7,11 +7,11 @@ use rustc_middle::{mir, ty::ParamEnv};
 impl<'tcx> Lower<'tcx> for mir::Constant<'tcx> {
     type Output = Constant;
 
-    fn lower(&self, lcx: LowerCtx<'tcx>) -> LowerResult<Self::Output> {
+    fn lower(&self, lcx: LowerCtx<'_, 'tcx>) -> LowerResult<Self::Output> {
         let bits = self
             .literal
             .try_eval_bits(lcx.tcx, ParamEnv::empty(), self.literal.ty())
-            .ok_or_else(|| todo!())?;
+            .ok_or_else(|| lcx.error(LowerErrorKind::Constant))?;
 
         let base_ty = self.literal.ty().lower(lcx)?;
 
@@ -1,7 +1,13 @@
-use crate::lower::{Lower, LowerResult};
+use crate::lower::{
+    result::{LowerError, LowerErrorKind},
+    Lower, LowerResult,
+};
 
 use liquid_rust_common::index::IndexVec;
-use liquid_rust_lrir::mir::Body;
+use liquid_rust_lrir::{
+    mir::{Body, Span},
+    ty,
+};
 
 use rustc_middle::{
     mir::{self, traversal::reverse_postorder},
@@ -9,14 +15,32 @@ use rustc_middle::{
 };
 
 #[derive(Clone, Copy)]
-pub(crate) struct LowerCtx<'tcx> {
+pub(crate) struct LowerCtx<'a, 'tcx> {
     pub tcx: TyCtxt<'tcx>,
+    /// The context where the structs and enums that can be refined are registered.
+    pub lr_tcx: &'a ty::TyCtxt,
     pub body: &'tcx mir::Body<'tcx>,
+    /// The span of the statement, terminator or local declaration being lowered. Used to report
+    /// errors.
+    pub span: Span,
 }
 
-impl<'tcx> LowerCtx<'tcx> {
-    pub fn lower_body(tcx: TyCtxt<'tcx>, body: &'tcx mir::Body<'tcx>) -> LowerResult<Body<'tcx>> {
-        let lcx = Self { tcx, body };
+impl<'a, 'tcx> LowerCtx<'a, 'tcx> {
+    pub fn new(tcx: TyCtxt<'tcx>, lr_tcx: &'a ty::TyCtxt, body: &'tcx mir::Body<'tcx>) -> Self {
+        Self {
+            tcx,
+            lr_tcx,
+            body,
+            span: body.span,
+        }
+    }
+
+    pub fn lower_body(
+        tcx: TyCtxt<'tcx>,
+        lr_tcx: &'a ty::TyCtxt,
+        body: &'tcx mir::Body<'tcx>,
+    ) -> LowerResult<Body<'tcx>> {
+        let lcx = Self::new(tcx, lr_tcx, body);
 
         let basic_blocks = body
             .basic_blocks()
@@ -24,7 +48,11 @@ impl<'tcx> LowerCtx<'tcx> {
             .map(|basic_block_data| basic_block_data.lower(lcx))
             .collect::<LowerResult<IndexVec<_, _>>>()?;
 
-        let rev_post = reverse_postorder(body).map(|(x, _)| x).collect::<Vec<_>>();
+        // Cleanup blocks only run while unwinding from a panic, so they are not checked.
+        let rev_post = reverse_postorder(body)
+            .filter(|(_, basic_block_data)| !basic_block_data.is_cleanup)
+            .map(|(x, _)| x)
+            .collect::<Vec<_>>();
 
         let local_decls = body
             .local_decls
@@ -47,4 +75,17 @@ impl<'tcx> LowerCtx<'tcx> {
             predecessors,
         })
     }
+
+    /// Returns a copy of this context for lowering the code at `span`.
+    pub fn with_span(self, span: Span) -> Self {
+        Self { span, ..self }
+    }
+
+    /// Returns an error for the code being lowered.
+    pub fn error(self, kind: LowerErrorKind) -> LowerError {
+        LowerError {
+            kind,
+            span: self.span,
+        }
+    }
 }
@@ -1,4 +1,4 @@
-use crate::lower::{Lower, LowerCtx, LowerResult};
+use crate::lower::{ty::check_supported_local, Lower, LowerCtx, LowerResult};
 
 use liquid_rust_lrir::mir::LocalDecl;
 
@@ -7,7 +7,9 @@ use rustc_middle::mir;
 impl<'tcx> Lower<'tcx> for mir::LocalDecl<'tcx> {
     type Output = LocalDecl<'tcx>;
 
-    fn lower(&self, _lcx: LowerCtx<'tcx>) -> LowerResult<Self::Output> {
+    fn lower(&self, lcx: LowerCtx<'_, 'tcx>) -> LowerResult<Self::Output> {
+        check_supported_local(self.ty, lcx.with_span(self.source_info.span))?;
+
         let output = LocalDecl {
             is_mutable: self.mutability == mir::Mutability::Mut,
             ty: self.ty,
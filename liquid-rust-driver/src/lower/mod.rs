@@ -10,12 +10,13 @@ mod statement;
 mod terminator;
 mod ty;
 
-use result::LowerResult;
+use result::{LowerErrorKind, LowerResult};
 
 pub(crate) use context::LowerCtx;
+pub(crate) use result::LowerError;
 
 pub(crate) trait Lower<'tcx> {
     type Output;
 
-    fn lower(&self, lcx: LowerCtx<'tcx>) -> LowerResult<Self::Output>;
+    fn lower(&self, lcx: LowerCtx<'_, 'tcx>) -> LowerResult<Self::Output>;
 }
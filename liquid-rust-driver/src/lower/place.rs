@@ -1,4 +1,4 @@
-use crate::lower::{Lower, LowerCtx, LowerResult};
+use crate::lower::{Lower, LowerCtx, LowerErrorKind, LowerResult};
 
 use liquid_rust_lrir::mir::{Place, PlaceElem};
 
@@ -7,7 +7,7 @@ use rustc_middle::mir;
 impl<'tcx> Lower<'tcx> for mir::Place<'tcx> {
     type Output = Place;
 
-    fn lower(&self, lcx: LowerCtx<'tcx>) -> LowerResult<Self::Output> {
+    fn lower(&self, lcx: LowerCtx<'_, 'tcx>) -> LowerResult<Self::Output> {
         Ok(Place {
             local: self.local,
             projection: self
@@ -22,12 +22,18 @@ impl<'tcx> Lower<'tcx> for mir::Place<'tcx> {
 impl<'tcx> Lower<'tcx> for mir::PlaceElem<'tcx> {
     type Output = PlaceElem;
 
-    fn lower(&self, _lcx: LowerCtx<'tcx>) -> LowerResult<Self::Output> {
+    fn lower(&self, lcx: LowerCtx<'_, 'tcx>) -> LowerResult<Self::Output> {
         let output = match self {
             Self::Deref => PlaceElem::Deref,
             // FIXME: Should we store the type too?
             Self::Field(field, _ty) => PlaceElem::Field(field.index()),
-            _ => todo!(),
+            Self::Index(_) | Self::ConstantIndex { .. } => {
+                return Err(lcx.error(LowerErrorKind::PlaceElem("indexing")));
+            }
+            Self::Subslice { .. } => {
+                return Err(lcx.error(LowerErrorKind::PlaceElem("subslice patterns")));
+            }
+            Self::Downcast(_, variant) => PlaceElem::Downcast(variant.index()),
         };
 
         Ok(output)
@@ -1,5 +1,7 @@
 use liquid_rust_lrir::mir::Span;
 
+use std::fmt;
+
 pub type LowerResult<T> = Result<T, LowerError>;
 
 pub struct LowerError {
@@ -7,4 +9,31 @@ pub struct LowerError {
     pub span: Span,
 }
 
-pub enum LowerErrorKind {}
+/// A Rust feature that cannot be lowered because it is not supported yet.
+pub enum LowerErrorKind {
+    /// An unsupported rvalue, e.g., a cast.
+    Rvalue(&'static str),
+    /// An unsupported statement, e.g., inline assembly.
+    Statement(&'static str),
+    /// An unsupported terminator, e.g., dropping a value.
+    Terminator(&'static str),
+    /// An unsupported place projection, e.g., indexing.
+    PlaceElem(&'static str),
+    /// An unsupported type, e.g., `&str`.
+    Ty(String),
+    /// A constant whose value cannot be computed, e.g., a generic constant.
+    Constant,
+}
+
+impl fmt::Display for LowerErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Rvalue(what)
+            | Self::Statement(what)
+            | Self::Terminator(what)
+            | Self::PlaceElem(what) => write!(f, "{}", what),
+            Self::Ty(ty) => write!(f, "values of type `{}`", ty),
+            Self::Constant => write!(f, "constants without a known value"),
+        }
+    }
+}
@@ -1,13 +1,16 @@
-use crate::lower::{Lower, LowerCtx, LowerResult};
+use crate::lower::{Lower, LowerCtx, LowerErrorKind, LowerResult};
 
-use liquid_rust_lrir::mir::{Rvalue, UnOp};
+use liquid_rust_lrir::{
+    mir::{AggregateKind, Rvalue, UnOp},
+    ty::{BorrowKind, Region},
+};
 
 use rustc_middle::mir;
 
 impl<'tcx> Lower<'tcx> for mir::Rvalue<'tcx> {
     type Output = Rvalue;
 
-    fn lower(&self, lcx: LowerCtx<'tcx>) -> LowerResult<Self::Output> {
+    fn lower(&self, lcx: LowerCtx<'_, 'tcx>) -> LowerResult<Self::Output> {
         let output = match self {
             Self::Use(operand) => Rvalue::Use(operand.lower(lcx)?),
             Self::UnaryOp(un_op, operand) => {
@@ -20,10 +23,61 @@ impl<'tcx> Lower<'tcx> for mir::Rvalue<'tcx> {
             Self::BinaryOp(bin_op, box (op1, op2)) => {
                 Rvalue::BinaryOp(*bin_op, op1.lower(lcx)?, op2.lower(lcx)?)
             }
+            Self::CheckedBinaryOp(bin_op, box (op1, op2)) => {
+                Rvalue::CheckedBinaryOp(*bin_op, op1.lower(lcx)?, op2.lower(lcx)?)
+            }
 
-            Self::Ref { .. } => todo!(),
-
-            _ => todo!("{:#?}", self),
+            Self::Ref(_, bk, place) => {
+                let bk = match bk {
+                    mir::BorrowKind::Shared => BorrowKind::Shared,
+                    mir::BorrowKind::Mut { .. } => BorrowKind::Mut,
+                    // Shallow and unique borrows are only created for match guards and closures.
+                    mir::BorrowKind::Shallow | mir::BorrowKind::Unique => {
+                        return Err(lcx.error(LowerErrorKind::Rvalue("match guards and closures")));
+                    }
+                };
+                let place = place.lower(lcx)?;
+                Rvalue::Ref(Region::from(place.clone()), bk, place)
+            }
+            Self::Repeat(..) => return Err(lcx.error(LowerErrorKind::Rvalue("array expressions"))),
+            Self::ThreadLocalRef(_) => {
+                return Err(lcx.error(LowerErrorKind::Rvalue("thread locals")));
+            }
+            Self::AddressOf(..) => return Err(lcx.error(LowerErrorKind::Rvalue("raw pointers"))),
+            Self::Len(_) => return Err(lcx.error(LowerErrorKind::Rvalue("slices"))),
+            Self::Cast(..) => return Err(lcx.error(LowerErrorKind::Rvalue("casts"))),
+            Self::NullaryOp(..) => {
+                return Err(lcx.error(LowerErrorKind::Rvalue("`size_of` and `box`")));
+            }
+            Self::Discriminant(place) => {
+                let discr_ty = self.ty(lcx.body, lcx.tcx).lower(lcx)?;
+                Rvalue::Discriminant(place.lower(lcx)?, discr_ty)
+            }
+            Self::Aggregate(box kind, operands) => {
+                let kind = match kind {
+                    mir::AggregateKind::Tuple => AggregateKind::Tuple,
+                    mir::AggregateKind::Array(_) => {
+                        return Err(lcx.error(LowerErrorKind::Rvalue("array expressions")));
+                    }
+                    // The type of the aggregate was already checked to be a supported struct or
+                    // enum.
+                    mir::AggregateKind::Adt(adt_def, variant, ..) if adt_def.is_enum() => {
+                        AggregateKind::Variant(variant.index())
+                    }
+                    mir::AggregateKind::Adt(adt_def, ..) => AggregateKind::Adt(adt_def.did),
+                    mir::AggregateKind::Closure(..) => {
+                        return Err(lcx.error(LowerErrorKind::Rvalue("closures")));
+                    }
+                    mir::AggregateKind::Generator(..) => {
+                        return Err(lcx.error(LowerErrorKind::Rvalue("generators")));
+                    }
+                };
+                let operands = operands
+                    .iter()
+                    .map(|operand| operand.lower(lcx))
+                    .collect::<LowerResult<Vec<_>>>()?;
+                Rvalue::Aggregate(kind, operands)
+            }
         };
 
         Ok(output)
@@ -1,4 +1,4 @@
-use crate::lower::{Lower, LowerCtx, LowerResult};
+use crate::lower::{Lower, LowerCtx, LowerErrorKind, LowerResult};
 
 use liquid_rust_lrir::mir::{Statement, StatementKind};
 
@@ -7,17 +7,48 @@ use rustc_middle::mir;
 impl<'tcx> Lower<'tcx> for mir::Statement<'tcx> {
     type Output = Statement;
 
-    fn lower(&self, lcx: LowerCtx<'tcx>) -> LowerResult<Self::Output> {
+    fn lower(&self, lcx: LowerCtx<'_, 'tcx>) -> LowerResult<Self::Output> {
+        let lcx = lcx.with_span(self.source_info.span);
+
         let kind = match &self.kind {
             mir::StatementKind::Assign(assign) => {
                 let (place, rvalue) = assign.as_ref();
 
-                StatementKind::Assign(place.lower(lcx)?, rvalue.lower(lcx)?)
+                // A reference argument points to the memory of the caller for the whole function.
+                let is_ref_arg = place.projection.is_empty()
+                    && lcx.body.args_iter().any(|arg| arg == place.local)
+                    && place.ty(lcx.body, lcx.tcx).ty.is_ref();
+                if is_ref_arg {
+                    let kind = LowerErrorKind::Statement("assignments to reference arguments");
+                    return Err(lcx.error(kind));
+                }
+
+                // Unit values carry no information. Optimized MIR doesn't assign them either.
+                if place.ty(lcx.body, lcx.tcx).ty.is_unit() {
+                    StatementKind::Nop
+                } else {
+                    StatementKind::Assign(place.lower(lcx)?, rvalue.lower(lcx)?)
+                }
             }
             mir::StatementKind::StorageLive(local) => StatementKind::StorageLive(*local),
             mir::StatementKind::StorageDead(local) => StatementKind::StorageDead(*local),
             mir::StatementKind::Nop => StatementKind::Nop,
-            _ => todo!(),
+            mir::StatementKind::SetDiscriminant {
+                place,
+                variant_index,
+            } => StatementKind::SetDiscriminant(place.lower(lcx)?, variant_index.index()),
+            mir::StatementKind::LlvmInlineAsm(_) => {
+                return Err(lcx.error(LowerErrorKind::Statement("inline assembly")));
+            }
+            mir::StatementKind::CopyNonOverlapping(_) => {
+                return Err(lcx.error(LowerErrorKind::Statement("`copy_nonoverlapping`")));
+            }
+            // These only matter to the borrow checker, Miri or code coverage and are removed from
+            // optimized MIR.
+            mir::StatementKind::FakeRead(..)
+            | mir::StatementKind::Retag(..)
+            | mir::StatementKind::AscribeUserType(..)
+            | mir::StatementKind::Coverage(_) => StatementKind::Nop,
         };
 
         Ok(Statement {
@@ -1,13 +1,18 @@
-use crate::lower::{Lower, LowerCtx, LowerResult};
+use crate::lower::{ty::check_supported_local, Lower, LowerCtx, LowerErrorKind, LowerResult};
 
-use liquid_rust_lrir::mir::{SwitchTargets, Terminator, TerminatorKind};
+use liquid_rust_lrir::mir::{AssertKind, SwitchTargets, Terminator, TerminatorKind};
 
-use rustc_middle::{mir, ty};
+use rustc_middle::{
+    mir,
+    ty::{self, subst::Subst, Instance},
+};
 
 impl<'tcx> Lower<'tcx> for mir::Terminator<'tcx> {
     type Output = Terminator<'tcx>;
 
-    fn lower(&self, lcx: LowerCtx<'tcx>) -> LowerResult<Self::Output> {
+    fn lower(&self, lcx: LowerCtx<'_, 'tcx>) -> LowerResult<Self::Output> {
+        let lcx = lcx.with_span(self.source_info.span);
+
         let kind = match &self.kind {
             mir::TerminatorKind::Return => TerminatorKind::Return,
             mir::TerminatorKind::Goto { target } => TerminatorKind::Goto { target: *target },
@@ -26,13 +31,34 @@ impl<'tcx> Lower<'tcx> for mir::Terminator<'tcx> {
                 destination,
                 ..
             } => {
-                let func = match func.ty(lcx.body, lcx.tcx).kind() {
+                let (fn_def, substs) = match func.ty(lcx.body, lcx.tcx).kind() {
                     ty::TyKind::FnDef(fn_def, substs) => (*fn_def, *substs),
                     _ => {
-                        unreachable!("Calling non function");
+                        let kind =
+                            LowerErrorKind::Terminator("calls to closures or function pointers");
+                        return Err(lcx.error(kind));
                     }
                 };
 
+                // The signature of the callee must be supported for the call to be checked. Its
+                // output only matters if the call returns.
+                let fn_sig = lcx.tcx.fn_sig(fn_def).subst(lcx.tcx, substs).skip_binder();
+                for ty in fn_sig.inputs() {
+                    check_supported_local(ty, lcx)?;
+                }
+                if destination.is_some() {
+                    check_supported_local(fn_sig.output(), lcx)?;
+                }
+
+                // Calls to trait methods are resolved to the implementation they dispatch to, if it
+                // is known. Otherwise, e.g. when calling through a generic `T: Trait` bound, the
+                // call keeps pointing to the trait method.
+                let param_env = lcx.tcx.param_env(lcx.body.source.def_id());
+                let func = match Instance::resolve(lcx.tcx, param_env, fn_def, substs) {
+                    Ok(Some(instance)) => (instance.def_id(), instance.substs),
+                    _ => (fn_def, substs),
+                };
+
                 TerminatorKind::Call {
                     func,
                     args: args
@@ -47,14 +73,49 @@ impl<'tcx> Lower<'tcx> for mir::Terminator<'tcx> {
             mir::TerminatorKind::Assert {
                 cond,
                 expected,
+                msg,
                 target,
                 ..
-            } => TerminatorKind::Assert {
-                cond: cond.lower(lcx)?,
-                expected: *expected,
-                target: *target,
+            } => {
+                let kind = match msg {
+                    mir::AssertKind::BoundsCheck { .. } => AssertKind::BoundsCheck,
+                    mir::AssertKind::Overflow(..) | mir::AssertKind::OverflowNeg(_) => {
+                        AssertKind::Overflow
+                    }
+                    mir::AssertKind::DivisionByZero(_) => AssertKind::DivisionByZero,
+                    mir::AssertKind::RemainderByZero(_) => AssertKind::RemainderByZero,
+                    mir::AssertKind::ResumedAfterReturn(_)
+                    | mir::AssertKind::ResumedAfterPanic(_) => {
+                        return Err(lcx.error(LowerErrorKind::Terminator("generators")));
+                    }
+                };
+
+                TerminatorKind::Assert {
+                    cond: cond.lower(lcx)?,
+                    expected: *expected,
+                    target: *target,
+                    kind,
+                }
+            }
+            mir::TerminatorKind::Resume => TerminatorKind::Resume,
+            mir::TerminatorKind::Abort => {
+                return Err(lcx.error(LowerErrorKind::Terminator("unwinding")));
+            }
+            mir::TerminatorKind::Unreachable => TerminatorKind::Unreachable,
+            mir::TerminatorKind::Drop { .. } | mir::TerminatorKind::DropAndReplace { .. } => {
+                return Err(lcx.error(LowerErrorKind::Terminator("types with destructors")));
+            }
+            mir::TerminatorKind::Yield { .. } | mir::TerminatorKind::GeneratorDrop => {
+                return Err(lcx.error(LowerErrorKind::Terminator("generators")));
+            }
+            // The imaginary edges of these terminators are only there for the borrow checker.
+            mir::TerminatorKind::FalseEdge { real_target, .. }
+            | mir::TerminatorKind::FalseUnwind { real_target, .. } => TerminatorKind::Goto {
+                target: *real_target,
             },
-            _ => todo!(),
+            mir::TerminatorKind::InlineAsm { .. } => {
+                return Err(lcx.error(LowerErrorKind::Terminator("inline assembly")));
+            }
         };
 
         Ok(Terminator {
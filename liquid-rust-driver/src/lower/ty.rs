@@ -1,4 +1,4 @@
-use crate::lower::{Lower, LowerCtx, LowerResult};
+use crate::lower::{Lower, LowerCtx, LowerErrorKind, LowerResult};
 
 use liquid_rust_lrir::ty::BaseTy;
 
@@ -7,14 +7,54 @@ use rustc_middle::ty;
 impl<'tcx> Lower<'tcx> for ty::Ty<'tcx> {
     type Output = BaseTy;
 
-    fn lower(&self, _lcx: LowerCtx<'tcx>) -> LowerResult<Self::Output> {
+    fn lower(&self, lcx: LowerCtx<'_, 'tcx>) -> LowerResult<Self::Output> {
         let output = match self.kind() {
             ty::TyKind::Bool => BaseTy::Bool,
-            // FIXME: maybe we should care about the size and sign.
-            ty::TyKind::Uint(_) | ty::TyKind::Int(_) => BaseTy::Int,
-            _ => todo!(),
+            ty::TyKind::Int(int_ty) => BaseTy::Int((*int_ty).into()),
+            ty::TyKind::Uint(uint_ty) => BaseTy::Int((*uint_ty).into()),
+            _ => return Err(lcx.error(LowerErrorKind::Ty(self.to_string()))),
         };
 
         Ok(output)
     }
 }
+
+/// Check that locals of type `ty` are supported, including arguments and the return place. A local
+/// may hold a reference to a supported value, but references are not supported anywhere else yet.
+pub(super) fn check_supported_local<'tcx>(
+    ty: ty::Ty<'tcx>,
+    lcx: LowerCtx<'_, 'tcx>,
+) -> LowerResult<()> {
+    match ty.kind() {
+        ty::TyKind::Ref(_, pointee, _) => check_supported(pointee, lcx),
+        _ => check_supported(ty, lcx),
+    }
+}
+
+/// Check that values of type `ty` are supported, i.e., that `ty` can be refined.
+fn check_supported<'tcx>(ty: ty::Ty<'tcx>, lcx: LowerCtx<'_, 'tcx>) -> LowerResult<()> {
+    match ty.kind() {
+        ty::TyKind::Tuple(_) => ty
+            .tuple_fields()
+            .try_for_each(|ty| check_supported(ty, lcx)),
+        // Only the structs and enums registered by the collector can be refined, see
+        // `Collector::collect_struct` and `collector::enum_def`. The fields of an enum may also be
+        // of its type arguments.
+        ty::TyKind::Adt(adt_def, _) if adt_def.is_struct() => {
+            match lcx.lr_tcx.adt_def(adt_def.did) {
+                Some(_) => Ok(()),
+                None => Err(lcx.error(LowerErrorKind::Ty(ty.to_string()))),
+            }
+        }
+        ty::TyKind::Adt(adt_def, substs) if adt_def.is_enum() => {
+            if lcx.lr_tcx.enum_def(adt_def.did).is_none() {
+                return Err(lcx.error(LowerErrorKind::Ty(ty.to_string())));
+            }
+            substs.types().try_for_each(|ty| check_supported(ty, lcx))
+        }
+        // There are no values of type `!`, but unoptimized MIR has locals of this type for
+        // diverging expressions.
+        ty::TyKind::Never => Ok(()),
+        _ => ty.lower(lcx).map(|_| ()),
+    }
+}
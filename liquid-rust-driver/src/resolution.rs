@@ -1,55 +1,205 @@
-use liquid_rust_common::index::IndexGen;
-use liquid_rust_lrir::ty::{self, Field, GhostVar, Path, Var};
+use liquid_rust_common::{index::IndexGen, ordered_map::OrderedMap};
+use liquid_rust_lrir::ty::{self, Field, GhostVar, Path, UniversalRegion, Var};
 use liquid_rust_parser::ast;
 use quickscope::ScopeMap;
+use rustc_hir::def_id::DefId;
+
+use std::collections::HashMap;
 
 pub struct Resolver<'src, 'a> {
     tcx: &'a ty::TyCtxt,
+    /// The module of the annotated item, if any. Spec files aren't in a module.
+    module: Option<DefId>,
     vars: ScopeMap<&'src str, Var>,
     ghost_gen: IndexGen<GhostVar>,
     fld_gen: IndexGen<Field>,
+    region_gen: IndexGen<UniversalRegion>,
+    /// The universal regions of the lifetime parameters of the function.
+    regions: HashMap<&'src str, UniversalRegion>,
+    /// The regions of the references resolved so far.
+    used_regions: Vec<UniversalRegion>,
+    /// The region of the references without a lifetime in the output. Like in Rust, it is the
+    /// region of the inputs if there is exactly one.
+    elided_region: Option<UniversalRegion>,
+    /// The variables bound to the pointees of the reference arguments, i.e., what `*x` refers to.
+    /// In the output and the `ensures` clause, the pointees of mutable references are the updated
+    /// ones.
+    derefs: ScopeMap<&'src str, Var>,
+    /// The variables bound to the pointees of the reference arguments before the call, i.e., what
+    /// `*x` refers to inside `old(..)`.
+    old_derefs: HashMap<&'src str, Var>,
+    /// Whether the predicate being resolved is inside `old(..)`.
+    in_old: bool,
+    /// The ghost variables bound to the pointees of the references resolved so far, which must be
+    /// bound before the references themselves.
+    pointees: Vec<(GhostVar, ty::Ty)>,
 }
 
 impl<'src, 'a> Resolver<'src, 'a> {
-    pub fn new(tcx: &'a ty::TyCtxt) -> Self {
+    pub fn new(tcx: &'a ty::TyCtxt, module: Option<DefId>) -> Self {
         Self {
             tcx,
+            module,
             vars: ScopeMap::new(),
             ghost_gen: IndexGen::new(),
             fld_gen: IndexGen::new(),
+            region_gen: IndexGen::new(),
+            regions: HashMap::new(),
+            used_regions: Vec::new(),
+            elided_region: None,
+            derefs: ScopeMap::new(),
+            old_derefs: HashMap::new(),
+            in_old: false,
+            pointees: Vec::new(),
         }
     }
 
-    pub fn resolve(&mut self, fn_decl: ast::FnDecl<'src>) -> ty::FnSig {
+    pub fn resolve(&mut self, fn_decl: ast::FnDecl<'src>) -> Result<ty::FnSig, ResolveError> {
+        for ident in fn_decl.regions {
+            let fresh_region = self.region_gen.fresh();
+            self.regions.insert(ident.symbol, fresh_region);
+        }
+
         let mut requires = Vec::new();
         let mut inputs = Vec::new();
+        let mut mut_args = Vec::new();
 
-        for (ident, ty) in fn_decl.inputs {
+        for (i, (ident, ty)) in fn_decl.inputs.into_iter().enumerate() {
             self.vars.push_layer();
             self.vars.define(ident.symbol, Var::Nu);
-            let ty = ty.resolve(self);
+            let ty = ty.resolve(self)?;
             self.vars.pop_layer();
 
             let fresh_gv = self.ghost_gen.fresh();
             self.vars.define(ident.symbol, Var::Ghost(fresh_gv));
+            if let ty::TyKind::Ref(bk, _, pointee) = ty.kind() {
+                self.derefs.define(ident.symbol, Var::Ghost(*pointee));
+                self.old_derefs.insert(ident.symbol, Var::Ghost(*pointee));
+                if *bk == ty::BorrowKind::Mut {
+                    mut_args.push((i, ident.symbol, *pointee));
+                }
+            }
 
+            requires.append(&mut self.pointees);
             requires.push((fresh_gv, ty));
             inputs.push(fresh_gv);
         }
+        let requires = requires.into_iter().collect::<OrderedMap<_, _>>();
+
+        // The pointees of mutable references are updated by the call, but they keep their type.
+        let mut ensures = Vec::new();
+        let mut outputs = Vec::new();
+        for (i, symbol, pointee) in &mut_args {
+            let fresh_gv = self.ghost_gen.fresh();
+            self.derefs.define(symbol, Var::Ghost(fresh_gv));
+            ensures.push((fresh_gv, requires[pointee].clone()));
+            outputs.push((*i, fresh_gv));
+        }
+
+        // The `ensures` clause refines the last updated pointee it can refine, which may depend
+        // on the others.
+        if let Some(pred) = fn_decl.ensures {
+            let (symbol, (_, ty)) = mut_args
+                .iter()
+                .map(|(_, symbol, _)| *symbol)
+                .zip(&mut ensures)
+                .rev()
+                .find(|(_, (_, ty))| matches!(ty.kind(), ty::TyKind::Refined(..)))
+                .ok_or(ResolveError::Ensures)?;
+            self.derefs.push_layer();
+            self.derefs.define(symbol, Var::Nu);
+            let pred = pred.resolve(self)?;
+            self.derefs.pop_layer();
+            if let ty::TyKind::Refined(bty, ty::Refine::Pred(refine)) = ty.kind() {
+                let refine = self.tcx.mk_bin_op(ty::BinOp::And, refine.clone(), pred);
+                *ty = self.tcx.mk_refine(*bty, refine);
+            }
+        }
+
+        if let [region] = self.used_regions[..] {
+            self.elided_region = Some(region);
+        }
 
         let output_gv = self.ghost_gen.fresh();
         let output_ty = match fn_decl.output {
-            Some(ty) => ty.resolve(self),
+            Some(ty) => ty.resolve(self)?,
             None => self.tcx.types.unit(),
         };
-        let ensures = vec![(output_gv, output_ty)];
+        ensures.append(&mut self.pointees);
+        ensures.push((output_gv, output_ty));
         let output = output_gv;
 
-        ty::FnSig {
-            requires: requires.into_iter().collect(),
+        Ok(ty::FnSig {
+            requires,
             inputs,
             ensures,
+            outputs,
             output,
+        })
+    }
+
+    /// Resolve the types of the fields of a struct, where `None` stands for a field without an
+    /// annotation. In the type of a field, `v` and the name of the field refer to its value, and
+    /// the names of the fields before it to their values.
+    pub fn resolve_fields(
+        &mut self,
+        fields: Vec<(&'src str, Option<ast::Ty<'src>>)>,
+    ) -> Result<Vec<Option<ty::Ty>>, ResolveError> {
+        let mut tys = Vec::new();
+        for (i, (name, ty)) in fields.into_iter().enumerate() {
+            let ty = match ty {
+                Some(ty) => {
+                    self.vars.push_layer();
+                    self.vars.define("v", Var::Nu);
+                    self.vars.define(name, Var::Nu);
+                    let ty = ty.resolve(self)?;
+                    self.vars.pop_layer();
+                    Some(ty)
+                }
+                None => None,
+            };
+            self.vars.define(name, Var::Field(Field::from_usize(i)));
+            tys.push(ty);
+        }
+        Ok(tys)
+    }
+}
+
+/// An annotation that parses but doesn't make sense.
+#[derive(Debug)]
+pub enum ResolveError {
+    /// An `ensures` clause in a signature without a mutable reference to a refined value.
+    Ensures,
+    /// A name that isn't a struct or an enum of the crate, `Option` or `Result`.
+    UnknownTy,
+    /// A struct or an enum with the wrong number of type arguments.
+    TyArgs,
+    /// A dereference `*x` of a name that isn't a reference argument.
+    NotARef,
+    /// A lifetime that isn't a parameter of the signature.
+    UnknownRegion,
+    /// A name of structs or enums of several modules, none of them the module of the annotated
+    /// item.
+    AmbiguousTy,
+}
+
+impl ResolveError {
+    pub fn message(&self) -> &'static str {
+        match self {
+            ResolveError::Ensures => {
+                "An `ensures` clause needs a mutable reference to a refined value."
+            }
+            ResolveError::UnknownTy => {
+                "Only the structs and enums of the crate, `Option` and `Result` can be named in types."
+            }
+            ResolveError::TyArgs => "Wrong number of type arguments.",
+            ResolveError::NotARef => "Only reference arguments can be dereferenced.",
+            ResolveError::UnknownRegion => {
+                "Only the lifetime parameters of the signature can be used in types."
+            }
+            ResolveError::AmbiguousTy => {
+                "Several modules define a type with this name, it can only be named in its module."
+            }
         }
     }
 }
@@ -61,21 +211,23 @@ trait Resolve<'src> {
 }
 
 impl<'src> Resolve<'src> for ast::Predicate<'src> {
-    type Output = ty::Pred;
+    type Output = Result<ty::Pred, ResolveError>;
 
     fn resolve(self, cx: &mut Resolver<'src, '_>) -> Self::Output {
         let tcx = cx.tcx;
         match self.kind {
             // FIXME: We should have a uniform way to represent constants.
-            ast::PredicateKind::Lit(ast::Literal::Bool(b)) => tcx.mk_const(ty::Constant::Bool(b)),
-            ast::PredicateKind::Lit(ast::Literal::Int(i)) => tcx.mk_const(ty::Constant::Int(i)),
+            ast::PredicateKind::Lit(ast::Literal::Bool(b)) => {
+                Ok(tcx.mk_const(ty::Constant::Bool(b)))
+            }
+            ast::PredicateKind::Lit(ast::Literal::Int(i)) => Ok(tcx.mk_const(ty::Constant::Int(i))),
             ast::PredicateKind::UnaryOp(un_op, op) => {
                 let un_op = match un_op.kind {
                     ast::UnOpKind::Not => ty::UnOp::Not,
                     ast::UnOpKind::Neg => ty::UnOp::Neg,
                 };
-                let op = op.resolve(cx);
-                tcx.mk_un_op(un_op, op)
+                let op = op.resolve(cx)?;
+                Ok(tcx.mk_un_op(un_op, op))
             }
             ast::PredicateKind::BinaryOp(bin_op, op1, op2) => {
                 let bin_op = match bin_op.kind {
@@ -93,27 +245,42 @@ impl<'src> Resolve<'src> for ast::Predicate<'src> {
                     ast::BinOpKind::Lte => ty::BinOp::Lte,
                     ast::BinOpKind::Gte => ty::BinOp::Gte,
                 };
-                let op1 = op1.resolve(cx);
-                let op2 = op2.resolve(cx);
-                tcx.mk_bin_op(bin_op, op1, op2)
+                let op1 = op1.resolve(cx)?;
+                let op2 = op2.resolve(cx)?;
+                Ok(tcx.mk_bin_op(bin_op, op1, op2))
+            }
+            ast::PredicateKind::Deref(ident) => {
+                let var = if cx.in_old {
+                    cx.old_derefs.get(ident.symbol)
+                } else {
+                    cx.derefs.get(ident.symbol)
+                };
+                let var = var.ok_or(ResolveError::NotARef)?;
+                Ok(tcx.mk_path(Path::from(*var)))
+            }
+            ast::PredicateKind::Old(pred) => {
+                let in_old = std::mem::replace(&mut cx.in_old, true);
+                let pred = pred.resolve(cx);
+                cx.in_old = in_old;
+                pred
             }
             ast::PredicateKind::Path(ident, projection) => {
                 let path = Path {
                     var: cx.vars[ident.symbol],
                     projection,
                 };
-                tcx.mk_path(path)
+                Ok(tcx.mk_path(path))
             }
         }
     }
 }
 
 impl<'src> Resolve<'src> for ast::Ty<'src> {
-    type Output = ty::Ty;
+    type Output = Result<ty::Ty, ResolveError>;
 
     fn resolve(self, cx: &mut Resolver<'src, '_>) -> Self::Output {
         let tcx = cx.tcx;
-        match self.kind {
+        let ty = match self.kind {
             ast::TyKind::Base(bty) => {
                 let bty = map_base_ty(bty);
                 tcx.mk_refine(bty, tcx.preds.tt())
@@ -122,11 +289,11 @@ impl<'src> Resolve<'src> for ast::Ty<'src> {
                 if let Some(ident) = refine.variable {
                     cx.vars.push_layer();
                     cx.vars.define(ident.symbol, Var::Nu);
-                    let pred = refine.refinement.resolve(cx);
+                    let pred = refine.refinement.resolve(cx)?;
                     cx.vars.pop_layer();
                     tcx.mk_refine(map_base_ty(refine.base_ty), pred)
                 } else {
-                    let pred = refine.refinement.resolve(cx);
+                    let pred = refine.refinement.resolve(cx)?;
                     tcx.mk_refine(map_base_ty(refine.base_ty), pred)
                 }
             }
@@ -139,18 +306,111 @@ impl<'src> Resolve<'src> for ast::Ty<'src> {
                         if let Some(ident) = fld {
                             cx.vars.push_layer();
                             cx.vars.define(ident.symbol, Var::Nu);
-                            let ty = ty.resolve(cx);
+                            let ty = ty.resolve(cx)?;
                             cx.vars.pop_layer();
                             cx.vars.define(ident.symbol, Var::Field(fresh_fld));
-                            (fresh_fld, ty)
+                            Ok((fresh_fld, ty))
                         } else {
-                            (fresh_fld, ty.resolve(cx))
+                            Ok((fresh_fld, ty.resolve(cx)?))
                         }
                     })
-                    .collect();
+                    .collect::<Result<_, _>>()?;
                 cx.vars.pop_layer();
                 tcx.mk_tuple(tup)
             }
+            ast::TyKind::Ref(region, ref_kind, ty) => {
+                let bk = match ref_kind {
+                    ast::RefKind::Shared => ty::BorrowKind::Shared,
+                    ast::RefKind::Mut => ty::BorrowKind::Mut,
+                };
+                let ty = ty.resolve(cx)?;
+                let fresh_gv = cx.ghost_gen.fresh();
+                cx.pointees.push((fresh_gv, ty));
+                let region = match (region, cx.elided_region) {
+                    (Some(ident), _) => *cx
+                        .regions
+                        .get(ident.symbol)
+                        .ok_or(ResolveError::UnknownRegion)?,
+                    (None, Some(region)) => region,
+                    (None, None) => cx.region_gen.fresh(),
+                };
+                if !cx.used_regions.contains(&region) {
+                    cx.used_regions.push(region);
+                }
+                let region = ty::Region::Abstract(region);
+                tcx.mk_ref(bk, region, fresh_gv)
+            }
+            ast::TyKind::Named(ident, args) => {
+                // A struct or an enum of the module of the annotated item shadows `Option` and
+                // `Result`, which shadow the ones of other modules.
+                let named = tcx
+                    .adt_defs_by_name(ident.symbol)
+                    .into_iter()
+                    .map(NamedTy::Adt)
+                    .chain(
+                        tcx.enum_defs_by_name(ident.symbol)
+                            .into_iter()
+                            .map(NamedTy::Enum),
+                    )
+                    .collect::<Vec<_>>();
+                let named = match named
+                    .iter()
+                    .find(|named| Some(named.module()) == cx.module)
+                    .or_else(|| named.iter().find(|named| !named.def_id().is_local()))
+                {
+                    Some(named) => named,
+                    None if named.len() > 1 => return Err(ResolveError::AmbiguousTy),
+                    None => named.first().ok_or(ResolveError::UnknownTy)?,
+                };
+                let enum_def = match named {
+                    NamedTy::Adt(adt_def) => {
+                        if !args.is_empty() {
+                            return Err(ResolveError::TyArgs);
+                        }
+                        return Ok(tcx.mk_adt_invariant(adt_def));
+                    }
+                    NamedTy::Enum(enum_def) => enum_def.clone(),
+                };
+                if args.len() != enum_def.params {
+                    return Err(ResolveError::TyArgs);
+                }
+                // The value of a field is `v` in its refinement.
+                let args = args
+                    .into_iter()
+                    .map(|ty| {
+                        cx.vars.push_layer();
+                        cx.vars.define("v", Var::Nu);
+                        let ty = ty.resolve(cx);
+                        cx.vars.pop_layer();
+                        ty
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                let variants = tcx.mk_enum_variants(&enum_def, &args);
+                tcx.mk_enum(enum_def, None, variants)
+            }
+        };
+        Ok(ty)
+    }
+}
+
+/// A struct or an enum a name in a type may refer to.
+enum NamedTy {
+    Adt(ty::AdtDef),
+    Enum(ty::EnumDef),
+}
+
+impl NamedTy {
+    fn def_id(&self) -> DefId {
+        match self {
+            NamedTy::Adt(adt_def) => adt_def.def_id,
+            NamedTy::Enum(enum_def) => enum_def.def_id,
+        }
+    }
+
+    fn module(&self) -> DefId {
+        match self {
+            NamedTy::Adt(adt_def) => adt_def.module,
+            NamedTy::Enum(enum_def) => enum_def.module,
         }
     }
 }
@@ -158,6 +418,24 @@ impl<'src> Resolve<'src> for ast::Ty<'src> {
 fn map_base_ty(ty: ast::BaseTy) -> ty::BaseTy {
     match ty {
         ast::BaseTy::Bool => ty::BaseTy::Bool,
-        ast::BaseTy::Int => ty::BaseTy::Int,
+        ast::BaseTy::Int => ty::BaseTy::AnyInt,
+        ast::BaseTy::IntTy(int_ty) => ty::BaseTy::Int(map_int_ty(int_ty)),
+    }
+}
+
+fn map_int_ty(int_ty: ast::IntTy) -> ty::IntTy {
+    match int_ty {
+        ast::IntTy::I8 => ty::IntTy::I8,
+        ast::IntTy::I16 => ty::IntTy::I16,
+        ast::IntTy::I32 => ty::IntTy::I32,
+        ast::IntTy::I64 => ty::IntTy::I64,
+        ast::IntTy::I128 => ty::IntTy::I128,
+        ast::IntTy::Isize => ty::IntTy::Isize,
+        ast::IntTy::U8 => ty::IntTy::U8,
+        ast::IntTy::U16 => ty::IntTy::U16,
+        ast::IntTy::U32 => ty::IntTy::U32,
+        ast::IntTy::U64 => ty::IntTy::U64,
+        ast::IntTy::U128 => ty::IntTy::U128,
+        ast::IntTy::Usize => ty::IntTy::Usize,
     }
 }
@@ -0,0 +1,38 @@
+#![feature(register_tool)]
+#![register_tool(liquid)]
+
+pub mod ints {
+    pub struct P {
+        pub x: i32,
+    }
+}
+
+pub mod bools {
+    pub struct P {
+        pub b: bool,
+    }
+}
+
+pub mod shapes {
+    pub enum Shape {
+        Circle(i32),
+    }
+}
+
+pub mod figures {
+    pub enum Shape {
+        Square(i32),
+    }
+}
+
+#[liquid::ty("fn(p: P) -> int")] //~ ERROR Several modules define a type with this name, it can only be named in its module.
+pub fn get(p: ints::P) -> i32 {
+    p.x
+}
+
+#[liquid::ty("fn(s: Shape) -> int")] //~ ERROR Several modules define a type with this name, it can only be named in its module.
+pub fn side(s: shapes::Shape) -> i32 {
+    match s {
+        shapes::Shape::Circle(r) => r,
+    }
+}
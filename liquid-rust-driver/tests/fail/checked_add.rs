@@ -0,0 +1,9 @@
+#![feature(register_tool)]
+#![register_tool(liquid)]
+
+// Fail tests are compiled without optimizations, so the addition is overflow checked. The result is
+// right whenever it doesn't overflow, so the only error is the possible overflow of `i32::MAX + 1`.
+#[liquid::ty("fn(n: {int | n >= 2147483646}) -> {v: int | v == n + 1}")]
+pub fn inc(n: i32) -> i32 {
+    n + 1 //~ ERROR possible arithmetic overflow
+}
@@ -0,0 +1,7 @@
+#![feature(register_tool)]
+#![register_tool(liquid)]
+
+#[liquid::ty("fn(x: &mut int) ensures *x == old(*x) + 1")]
+pub fn incr(x: &mut i32) {
+    *x += 2;
+}
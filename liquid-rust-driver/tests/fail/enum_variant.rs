@@ -0,0 +1,10 @@
+#![feature(register_tool)]
+#![register_tool(liquid)]
+
+#[liquid::ty("fn(x: Option<int>) -> {v: int | v > 0}")]
+pub fn unwrap_or_one(x: Option<i32>) -> i32 {
+    match x {
+        Some(n) => n,
+        None => 1,
+    }
+}
@@ -0,0 +1,7 @@
+#![feature(register_tool)]
+#![register_tool(liquid)]
+
+#[liquid::ty("fn(n: int) -> {v: int | v >= 0}")]
+pub fn signed(n: isize) -> isize {
+    n
+}
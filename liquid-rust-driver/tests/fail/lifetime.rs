@@ -0,0 +1,23 @@
+#![feature(register_tool)]
+#![register_tool(liquid)]
+
+#[liquid::ty("fn<'a>(x: &'a mut {v: int | v >= 0}) -> &'a mut {v: int | v >= 0}")]
+pub fn id_mut<'a>(x: &'a mut i32) -> &'a mut i32 {
+    x
+}
+
+#[liquid::ty("fn() -> {v: int | v >= 0}")]
+pub fn write_through_result() -> i32 {
+    let mut x = 0;
+    let r = id_mut(&mut x);
+    *r = -1;
+    x
+}
+
+#[liquid::ty("fn(x: &mut {v: int | v >= 0}, b: bool) -> int")]
+pub fn pick_local(x: &mut i32, b: bool) -> i32 {
+    let mut y = 0;
+    let r = if b { x } else { &mut y };
+    *r -= 1;
+    *r
+}
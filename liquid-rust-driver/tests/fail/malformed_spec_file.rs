@@ -0,0 +1,10 @@
+#![feature(register_tool)]
+#![register_tool(liquid)]
+
+// The spec files given to this test don't start with a function signature, see the annotations in
+// them.
+
+#[liquid::ty("fn(x: int) -> int")]
+pub fn id(x: i32) -> i32 {
+    x
+}
@@ -0,0 +1,9 @@
+#![feature(register_tool)]
+#![register_tool(liquid)]
+
+// The spec file given to this test through `-Lspec_files` doesn't exist.
+
+#[liquid::ty("fn(x: int) -> int")]
+pub fn id(x: i32) -> i32 {
+    x
+}
@@ -1,5 +1,14 @@
+use std::{
+    collections::HashSet,
+    io::{self, Write},
+    sync::{Arc, Mutex},
+};
+
+/// Check that the test `$name` fails to verify and reports the errors annotated in it and in the
+/// spec files it is given, see [check_annotations] and [check_spec_annotations]. Additional arguments, e.g., Liquid Rust settings like
+/// `-Lpanic_freedom=true`, are passed after the default ones.
 macro_rules! fail_test {
-    ($name:ident) => {
+    ($name:ident $(, $arg:expr)*) => {
         #[test]
         fn $name() {
             let home = env!("RUSTUP_HOME");
@@ -7,16 +16,120 @@ macro_rules! fail_test {
             let sysroot = format!("--sysroot={}/toolchains/{}", home, toolchain);
 
             let path = concat!("tests/fail/", stringify!($name), ".rs");
-            let code = liquid_rust_driver::run_compiler(vec![
-                "whatever".into(),
-                "-Znll-facts".into(),
-                path.into(),
-                sysroot.into(),
-                "--crate-type=lib".into(),
-            ]);
+            let output = Output::default();
+            let code = liquid_rust_driver::run_compiler_with_output(
+                vec![
+                    "whatever".into(),
+                    path.into(),
+                    sysroot.into(),
+                    "--crate-type=lib".into(),
+                    $($arg.into(),)*
+                ],
+                Some(Box::new(output.clone())),
+            );
+            let output = output.contents();
+            eprint!("{}", output);
             assert!(code != 0);
+            check_annotations(path, &output);
+            let args: &[&str] = &[$($arg),*];
+            for arg in args {
+                if let Some(files) = arg.strip_prefix("-Lspec_files=") {
+                    files.split(',').for_each(|file| check_spec_annotations(file, &output));
+                }
+            }
         }
     };
 }
 
+/// The diagnostics of a compilation, written by the compiler.
+#[derive(Clone, Default)]
+struct Output(Arc<Mutex<Vec<u8>>>);
+
+impl Output {
+    fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.0.lock().unwrap()).into_owned()
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Check that every error annotated in the test at `path` with a `//~ ERROR <message>` comment is
+/// reported in `output` with that message, on the line of the comment.
+fn check_annotations(path: &str, output: &str) {
+    // The message and line of every reported error, e.g., `error: possible division by zero`
+    // followed by ` --> tests/fail/division.rs:7:5`.
+    let lines = output.lines().collect::<Vec<_>>();
+    let reported = lines
+        .windows(2)
+        .filter_map(|w| {
+            let message = w[0].strip_prefix("error: ")?;
+            let location = w[1].trim_start().strip_prefix("--> ")?;
+            let line = location.rsplitn(3, ':').nth(1)?.parse::<usize>().ok()?;
+            Some((message, line))
+        })
+        .collect::<HashSet<_>>();
+
+    let source = std::fs::read_to_string(path).unwrap();
+    for (i, line) in source.lines().enumerate() {
+        if let Some((_, message)) = line.split_once("//~ ERROR ") {
+            assert!(
+                reported.contains(&(message.trim(), i + 1)),
+                "expected error `{}` on line {}",
+                message.trim(),
+                i + 1
+            );
+        }
+    }
+}
+
+/// Check that every error annotated in the spec file at `path` with a `//~ ERROR <message>` comment
+/// is reported in `output`, e.g., `error: tests/fail/specs.lrspec:3: Unexpected token.`. A spec file
+/// that doesn't exist has no annotations.
+fn check_spec_annotations(path: &str, output: &str) {
+    let source = match std::fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(_) => return,
+    };
+    for (i, line) in source.lines().enumerate() {
+        if let Some((_, message)) = line.split_once("//~ ERROR ") {
+            let error = format!("error: {}:{}: {}", path, i + 1, message.trim());
+            assert!(
+                output.lines().any(|line| line == error),
+                "expected `{}`",
+                error
+            );
+        }
+    }
+}
+
+fail_test!(ambiguous_ty);
+fail_test!(checked_add, "-Lpanic_freedom=true");
+fail_test!(enum_variant);
+fail_test!(ensures);
+fail_test!(int_range);
+fail_test!(lifetime);
+fail_test!(
+    malformed_spec_file,
+    "-Lspec_files=tests/fail/malformed_spec_file.lrspec,tests/fail/multibyte_spec_file.lrspec"
+);
+fail_test!(missing_spec_file, "-Lspec_files=tests/fail/missing.lrspec");
+fail_test!(mut_ref);
+fail_test!(not_a_ref);
 fail_test!(one);
+fail_test!(overflow, "-O");
+fail_test!(panic_freedom, "-Lpanic_freedom=true");
+fail_test!(shared_ref);
+fail_test!(struct_invariant);
+fail_test!(trait_impl);
+fail_test!(tuple_projection);
+fail_test!(unknown_region);
+fail_test!(unsupported);
+fail_test!(wrapping, "-O", "-Lint_model=wrapping");
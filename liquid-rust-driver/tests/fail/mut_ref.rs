@@ -0,0 +1,7 @@
+#![feature(register_tool)]
+#![register_tool(liquid)]
+
+#[liquid::ty("fn(x: &mut {v: int | v >= 0})")]
+pub fn decr(x: &mut i32) {
+    *x -= 1;
+}
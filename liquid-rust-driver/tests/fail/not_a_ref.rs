@@ -0,0 +1,12 @@
+#![feature(register_tool)]
+#![register_tool(liquid)]
+
+#[liquid::ty("fn(x: int) -> {v: int | v == *x}")] //~ ERROR Only reference arguments can be dereferenced.
+pub fn get(x: i32) -> i32 {
+    x
+}
+
+#[liquid::ty("fn(x: &mut int) ensures *x == old(*y)")] //~ ERROR Only reference arguments can be dereferenced.
+pub fn reset(x: &mut i32) {
+    *x = 0;
+}
@@ -0,0 +1,19 @@
+#![feature(register_tool)]
+#![register_tool(liquid)]
+
+// Every result is right if it doesn't overflow, so each function only fails because it can.
+
+#[liquid::ty("fn(x: int) -> {v: int | v > x}")]
+pub fn succ(x: i32) -> i32 {
+    x + 1
+}
+
+#[liquid::ty("fn(x: int) -> int")]
+pub fn neg(x: i32) -> i32 {
+    -x
+}
+
+#[liquid::ty("fn(x: int) -> {v: int | v == 2 * x}")]
+pub fn double(x: u8) -> u8 {
+    x * 2
+}
@@ -0,0 +1,22 @@
+#![feature(register_tool)]
+#![register_tool(liquid)]
+
+#[liquid::ty("fn(x: int, y: int) -> int")]
+pub fn div(x: u32, y: u32) -> u32 {
+    x / y //~ ERROR possible division by zero
+}
+
+#[liquid::ty("fn(x: int, y: {int | y != 0}) -> int")]
+pub fn rem(x: i32, y: i32) -> i32 {
+    x % y //~ ERROR possible arithmetic overflow
+}
+
+#[liquid::ty("fn(x: {int | x < 100}) -> int")]
+pub fn succ(x: i32) -> i32 {
+    x + 1
+}
+
+#[liquid::ty("fn(x: int) -> int")]
+pub fn pred(x: i32) -> i32 {
+    x - 1 //~ ERROR possible arithmetic overflow
+}
@@ -0,0 +1,7 @@
+#![feature(register_tool)]
+#![register_tool(liquid)]
+
+#[liquid::ty("fn(x: &{v: int | v >= 0}) -> {v: int | v > 0}")]
+pub fn get(x: &i32) -> i32 {
+    *x
+}
@@ -0,0 +1,13 @@
+#![feature(register_tool)]
+#![register_tool(liquid)]
+
+pub struct Range {
+    lo: i32,
+    #[liquid::field("{int | v >= lo}")]
+    hi: i32,
+}
+
+#[liquid::ty("fn(r: &mut Range)")]
+pub fn reset_lo(r: &mut Range) {
+    r.lo = 10;
+}
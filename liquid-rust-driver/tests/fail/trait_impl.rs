@@ -0,0 +1,16 @@
+#![feature(register_tool)]
+#![register_tool(liquid)]
+
+pub trait Nat {
+    #[liquid::ty("fn(n: {int | n >= 0}) -> {v: int | v >= 0}")]
+    fn step(n: i32) -> i32;
+}
+
+pub struct Id;
+
+impl Nat for Id {
+    #[liquid::ty("fn(n: {int | n > 0}) -> {v: int | v == n}")]
+    fn step(n: i32) -> i32 {
+        n
+    }
+}
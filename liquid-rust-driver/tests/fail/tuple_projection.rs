@@ -0,0 +1,7 @@
+#![feature(register_tool)]
+#![register_tool(liquid)]
+
+#[liquid::ty("fn(p: (a: int, b: int)) -> (c: {int | c == p.1}, {d: int | d == p.0})")]
+pub fn swap(p: (i32, i32)) -> (i32, i32) {
+    (p.0, p.1)
+}
@@ -0,0 +1,7 @@
+#![feature(register_tool)]
+#![register_tool(liquid)]
+
+#[liquid::ty("fn<'a>(x: &'b int) -> int")] //~ ERROR Only the lifetime parameters of the signature can be used in types.
+pub fn get<'a, 'b>(x: &'b i32) -> i32 {
+    *x
+}
@@ -0,0 +1,37 @@
+#![feature(register_tool)]
+#![register_tool(liquid)]
+
+#[liquid::ty("fn(x: bool) -> int")]
+pub fn cast(x: bool) -> i32 {
+    x as i32 //~ ERROR liquid-rust does not support casts yet
+}
+
+#[liquid::ty("fn() -> {v: int | v == 1}")]
+pub fn one() -> i32 {
+    1
+}
+
+#[liquid::ty("fn(x: int) -> int")]
+pub fn panics(x: i32) -> i32 {
+    if x > 0 {
+        panic!() //~ ERROR liquid-rust does not support values of type `str` yet
+    }
+    x
+}
+
+pub struct Invalid {
+    #[liquid::field("{bool | v}")] //~ ERROR Type annotation doesn't match the type of the field.
+    x: i32,
+}
+
+#[liquid::ty("fn() -> int")]
+pub fn invalid() -> i32 {
+    let s = Invalid { x: 1 }; //~ ERROR liquid-rust does not support values of type `Invalid` yet
+    s.x
+}
+
+#[liquid::ty("fn<'a>(x: &'a mut int, y: &'a mut int)")]
+pub fn reassign_arg<'a>(mut x: &'a mut i32, y: &'a mut i32) {
+    x = y; //~ ERROR liquid-rust does not support assignments to reference arguments yet
+    *x = 1;
+}
@@ -0,0 +1,8 @@
+#![feature(register_tool)]
+#![register_tool(liquid)]
+
+// The addition isn't checked for overflow, so it wraps around to 0 for 255.
+#[liquid::ty("fn(x: int) -> {v: int | v > x}")]
+pub fn succ(x: u8) -> u8 {
+    x + 1
+}
@@ -0,0 +1,9 @@
+#![feature(register_tool)]
+#![register_tool(liquid)]
+
+#[liquid::ty("fn(x: int, y: int) -> {v: int | v != 0}")]
+pub fn divisor(x: u32, y: u32) -> u32 {
+    let _q = x / y;
+    // The division panics unless `y` is nonzero.
+    y
+}
@@ -0,0 +1,14 @@
+#![feature(register_tool)]
+#![register_tool(liquid)]
+
+// The body of an assumed function is never checked, so it can use features that are not supported
+// yet like casts and floating point numbers.
+#[liquid::assume("fn(n: {int | n >= 0}) -> {v: int | v >= 0 && v <= n}")]
+pub fn isqrt(n: u32) -> u32 {
+    (n as f64).sqrt() as u32
+}
+
+#[liquid::ty("fn(n: {int | n >= 0}) -> {v: int | v <= n}")]
+pub fn isqrt_twice(n: u32) -> u32 {
+    isqrt(isqrt(n))
+}
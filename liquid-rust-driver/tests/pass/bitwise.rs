@@ -0,0 +1,31 @@
+#![feature(register_tool)]
+#![register_tool(liquid)]
+
+#[liquid::ty("fn(x: int) -> {v: int | v <= 255}")]
+pub fn low_byte(x: u32) -> u32 {
+    x & 0xff
+}
+
+#[liquid::ty("fn(x: int, y: int) -> {v: int | v >= x}")]
+pub fn set_bits(x: u32, y: u32) -> u32 {
+    x | y
+}
+
+#[liquid::ty("fn(x: {int | x < 256}) -> {v: int | v == x * 16}")]
+pub fn times_sixteen(x: u32) -> u32 {
+    x << 4
+}
+
+#[liquid::ty("fn(x: int) -> {v: int | v <= x}")]
+pub fn halve(x: u32) -> u32 {
+    x >> 1
+}
+
+#[liquid::ty("fn(a: bool) -> {v: int | v == 0}")]
+pub fn xor_self(a: bool) -> i32 {
+    if a ^ a {
+        1
+    } else {
+        0
+    }
+}
@@ -1,7 +1,7 @@
 #![feature(register_tool)]
 #![register_tool(liquid)]
 
-#[liquid::ty("fn(n: int) -> { v: int | v >= 0 }")]
+#[liquid::ty("fn(n: {int | n > -2147483648}) -> { v: int | v >= 0 }")]
 pub fn abs(n: i32) -> i32 {
     if n < 0 {
         -n
@@ -10,7 +10,7 @@ pub fn abs(n: i32) -> i32 {
     }
 }
 
-#[liquid::ty("fn(n: int) -> { v: int | v >= 0 }")]
+#[liquid::ty("fn(n: {int | n > -2147483648}) -> { v: int | v >= 0 }")]
 pub fn abs_mut(mut n: i32) -> i32 {
     if n < 0 {
         n = -n;
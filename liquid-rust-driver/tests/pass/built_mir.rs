@@ -0,0 +1,25 @@
+#![feature(register_tool)]
+#![register_tool(liquid)]
+
+// The built MIR has false edges for the arms of a `match` and false unwinds for loops.
+#[liquid::ty("fn(x: Option<{int | v > 0}>) -> {v: int | v > 0}")]
+pub fn unwrap_or_one(x: Option<i32>) -> i32 {
+    match x {
+        Some(n) => n,
+        None => 1,
+    }
+}
+
+#[liquid::ty("fn(n: {int | n >= 0}) -> { v: int | v == n }")]
+pub fn id_while(n: i32) -> i32 {
+    let mut i = 0;
+    while i < n {
+        i += 1;
+    }
+    i
+}
+
+#[liquid::ty("fn(n: {int | n < 100}) -> {v: int | v > n}")]
+pub const fn succ(n: i32) -> i32 {
+    n + 1
+}
@@ -0,0 +1,18 @@
+#![feature(register_tool)]
+#![register_tool(liquid)]
+
+// This test is compiled with overflow checks, so every operation asserts that it doesn't overflow.
+#[liquid::ty("fn(n: {int | n < 2147483647}) -> {v: int | v == n + 1}")]
+pub fn inc(n: i32) -> i32 {
+    n + 1
+}
+
+#[liquid::ty("fn(x: {int | x <= 100}, y: {int | y <= 155}) -> {v: int | v == x + y}")]
+pub fn add(x: u8, y: u8) -> u8 {
+    x + y
+}
+
+#[liquid::ty("fn(x: int, y: {int | y <= x}) -> {v: int | v == x - y}")]
+pub fn sub(x: u32, y: u32) -> u32 {
+    x - y
+}
@@ -0,0 +1,19 @@
+#![feature(register_tool)]
+#![register_tool(liquid)]
+
+// Division and remainder truncate toward zero.
+
+#[liquid::ty("fn(x: {int | x < 0 && x > -2}) -> {v: int | v == 0}")]
+pub fn half_of_minus_one(x: i32) -> i32 {
+    x / 2
+}
+
+#[liquid::ty("fn(x: {int | x < 0}) -> {v: int | v <= 0}")]
+pub fn rem_negative(x: i32) -> i32 {
+    x % 2
+}
+
+#[liquid::ty("fn(x: int, y: {int | y > 0}) -> {v: int | v < y}")]
+pub fn rem_bounded(x: i32, y: i32) -> i32 {
+    x % y
+}
@@ -0,0 +1,45 @@
+#![feature(register_tool)]
+#![register_tool(liquid)]
+
+// This test is compiled with overflow checks, so incrementing `i32::MAX` panics instead of wrapping
+// around.
+#[liquid::ty("fn(x: &mut int) ensures *x == old(*x) + 1")]
+pub fn incr(x: &mut i32) {
+    *x += 1;
+}
+
+#[liquid::ty("fn() -> {v: int | v == 2}")]
+pub fn call_incr() -> i32 {
+    let mut x = 0;
+    incr(&mut x);
+    incr(&mut x);
+    x
+}
+
+#[liquid::ty("fn(x: &mut int) -> {v: int | v == *x} ensures *x == old(*x) + 1")]
+pub fn incr_and_get(x: &mut i32) -> i32 {
+    *x += 1;
+    *x
+}
+
+#[liquid::ty("fn(x: &mut int, y: &mut int) ensures *x == old(*y) && *y == old(*x)")]
+pub fn swap(x: &mut i32, y: &mut i32) {
+    let t = *x;
+    *x = *y;
+    *y = t;
+}
+
+#[liquid::ty("fn() -> {v: int | v == 1}")]
+pub fn call_swap() -> i32 {
+    let mut a = 1;
+    let mut b = 2;
+    swap(&mut a, &mut b);
+    b
+}
+
+#[liquid::ty("fn(x: &mut {v: int | v >= 0}) ensures *x >= old(*x)")]
+pub fn grow(x: &mut i32) {
+    if *x < 100 {
+        *x *= 2;
+    }
+}
@@ -0,0 +1,51 @@
+#![feature(register_tool)]
+#![register_tool(liquid)]
+
+pub enum Shape {
+    Circle(i32),
+    Square(i32, bool),
+    Empty,
+}
+
+#[liquid::ty("fn(x: Option<{int | v > 0}>) -> {v: int | v > 0}")]
+pub fn unwrap_or_one(x: Option<i32>) -> i32 {
+    match x {
+        Some(n) => n,
+        None => 1,
+    }
+}
+
+#[liquid::ty("fn(x: {int | x >= 0}) -> Option<{int | v > 0}>")]
+pub fn succ(x: i32) -> Option<i32> {
+    if x < 100 {
+        Some(x + 1)
+    } else {
+        None
+    }
+}
+
+#[liquid::ty("fn(r: Result<{int | v >= 0}, bool>) -> {v: int | v >= 0}")]
+pub fn ok_or_zero(r: Result<i32, bool>) -> i32 {
+    match r {
+        Ok(n) => n,
+        Err(_) => 0,
+    }
+}
+
+#[liquid::ty("fn(s: Shape) -> {v: int | v >= 0}")]
+pub fn corners(s: Shape) -> i32 {
+    match s {
+        Shape::Circle(_) => 0,
+        Shape::Square(_, _) => 4,
+        Shape::Empty => 0,
+    }
+}
+
+#[liquid::ty("fn() -> {v: int | v > 0}")]
+pub fn known_variant() -> i32 {
+    let s = Some(5);
+    match s {
+        Some(n) => n,
+        None => 1,
+    }
+}
@@ -0,0 +1,15 @@
+#![feature(register_tool)]
+#![register_tool(liquid)]
+
+#[liquid::ty("fn() -> {v: int | v > 0}")]
+pub fn write_through_result() -> i32 {
+    let mut x = 0;
+    let r = extern_crate_lib::id_mut(&mut x);
+    *r = 5;
+    x
+}
+
+#[liquid::ty("fn() -> {v: int | v > 1}")]
+pub fn two() -> i32 {
+    extern_crate_lib::succ(1)
+}
@@ -0,0 +1,14 @@
+#![feature(register_tool)]
+#![register_tool(liquid)]
+
+// The library the `extern_crate` test depends on.
+
+#[liquid::ty("fn<'a>(x: &'a mut {v: int | v >= 0}) -> &'a mut {v: int | v >= 0}")]
+pub fn id_mut<'a>(x: &'a mut i32) -> &'a mut i32 {
+    x
+}
+
+#[liquid::ty("fn(x: {int | x < 100}) -> {v: int | v > x}")]
+pub fn succ(x: i32) -> i32 {
+    x + 1
+}
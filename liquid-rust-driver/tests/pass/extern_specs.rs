@@ -0,0 +1,22 @@
+#![feature(register_tool)]
+#![register_tool(liquid)]
+
+#[liquid::ty("fn(x: int) -> {v: int | v >= 0}")]
+pub fn clamp_neg(x: i32) -> i32 {
+    std::cmp::max(x, 0)
+}
+
+#[liquid::ty("fn(x: int, y: int) -> {v: int | v <= x}")]
+pub fn min(x: i32, y: i32) -> i32 {
+    x.min(y)
+}
+
+#[liquid::ty("fn(x: int) -> {v: int | v >= -1 && v <= 1}")]
+pub fn sign(x: i32) -> i32 {
+    x.signum()
+}
+
+#[liquid::ty("fn(x: int) -> {v: int | v >= 0 && v < 10}")]
+pub fn digit(x: i32) -> i32 {
+    x.rem_euclid(10)
+}
@@ -0,0 +1,17 @@
+#![feature(register_tool)]
+#![register_tool(liquid)]
+
+#[liquid::ty("fn(n: int) -> {v: int | v >= 0}")]
+pub fn unsigned(n: usize) -> usize {
+    n
+}
+
+#[liquid::ty("fn(n: i8) -> {v: int | v <= 127}")]
+pub fn byte(n: i8) -> i8 {
+    n
+}
+
+#[liquid::ty("fn() -> {v: i32 | v < 0}")]
+pub fn minus_one() -> i32 {
+    -1
+}
@@ -0,0 +1,64 @@
+#![feature(register_tool)]
+#![register_tool(liquid)]
+
+#[liquid::ty("fn<'a>(x: &'a mut {v: int | v >= 0}) -> &'a mut {v: int | v >= 0}")]
+pub fn id_mut<'a>(x: &'a mut i32) -> &'a mut i32 {
+    x
+}
+
+#[liquid::ty("fn() -> {v: int | v > 0}")]
+pub fn write_through_result() -> i32 {
+    let mut x = 0;
+    let r = id_mut(&mut x);
+    *r = 5;
+    x
+}
+
+#[liquid::ty(
+    "fn<'a>(c: bool, x: &'a {v: int | v >= 0}, y: &'a {v: int | v >= 0}) -> &'a {v: int | v >= 0}"
+)]
+pub fn pick<'a>(c: bool, x: &'a i32, y: &'a i32) -> &'a i32 {
+    if c {
+        x
+    } else {
+        y
+    }
+}
+
+#[liquid::ty("fn(c: bool) -> {v: int | v >= 0}")]
+pub fn call_pick(c: bool) -> i32 {
+    let x = 1;
+    let y = 2;
+    *pick(c, &x, &y)
+}
+
+#[liquid::ty("fn(x: &mut {v: int | v > 0}) -> &mut {v: int | v > 0}")]
+pub fn elided(x: &mut i32) -> &mut i32 {
+    *x = 1;
+    x
+}
+
+pub fn unannotated(x: &mut i32) -> &mut i32 {
+    x
+}
+
+#[liquid::ty("fn() -> {v: int | v > 0}")]
+pub fn write_through_unannotated() -> i32 {
+    let mut x = 0;
+    *unannotated(&mut x) = 3;
+    x
+}
+
+#[liquid::ty("fn(x: &mut {v: int | v >= 0}, b: bool) -> {v: int | v > 0}")]
+pub fn pick_local(x: &mut i32, b: bool) -> i32 {
+    let mut y = 0;
+    let r = if b { x } else { &mut y };
+    *r += 1;
+    *r
+}
+
+#[liquid::ty("fn(x: &{v: int | v >= 0}, c: bool) -> {v: int | v >= 0}")]
+pub fn call_pick_local(x: &i32, c: bool) -> i32 {
+    let y = 1;
+    *pick(c, x, &y)
+}
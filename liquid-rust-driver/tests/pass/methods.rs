@@ -0,0 +1,39 @@
+#![feature(register_tool)]
+#![register_tool(liquid)]
+
+pub struct Math;
+
+impl Math {
+    #[liquid::ty("fn(n: {int | n < 2147483647}) -> {v: int | v == n + 1}")]
+    pub fn incr(n: i32) -> i32 {
+        n + 1
+    }
+}
+
+pub trait Shift {
+    fn shift(self, n: i32) -> i32;
+
+    #[liquid::ty("fn(n: {int | n < 2147483646}) -> {v: int | v == n + 2}")]
+    fn offset(n: i32) -> i32 {
+        Math::incr(Math::incr(n))
+    }
+}
+
+impl Shift for i32 {
+    #[liquid::ty(
+        "fn(self: {int | self < 1000}, n: {int | n >= 0 && n < 1000}) -> {v: int | v == self + n}"
+    )]
+    fn shift(self, n: i32) -> i32 {
+        self + n
+    }
+}
+
+#[liquid::ty("fn(x: {int | x < 100}) -> {v: int | v == x + 2}")]
+pub fn shift_twice(x: i32) -> i32 {
+    x.shift(1).shift(1)
+}
+
+#[liquid::ty("fn(x: {int | x < 2147483646}) -> {v: int | v == x + 2}")]
+pub fn offset(x: i32) -> i32 {
+    <i32 as Shift>::offset(x)
+}
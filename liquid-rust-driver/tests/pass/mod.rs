@@ -1,5 +1,7 @@
+/// Check that the test `$name` verifies. Additional arguments, e.g., Liquid Rust settings like
+/// `-Lpanic_freedom=true`, are passed after the default ones.
 macro_rules! pass_test {
-    ($name:ident) => {
+    ($name:ident $(, $arg:expr)*) => {
         #[test]
         fn $name() {
             let home = env!("RUSTUP_HOME");
@@ -10,18 +12,78 @@ macro_rules! pass_test {
 
             let code = liquid_rust_driver::run_compiler(vec![
                 "whatever".into(),
-                "-Znll-facts".into(),
                 path.into(),
                 sysroot.into(),
                 "-O".into(),
                 "--crate-type=lib".into(),
+                $($arg.into(),)*
             ]);
             assert!(code == 0);
         }
     };
 }
 
+pass_test!(asserts);
+pass_test!(assume);
+pass_test!(bitwise);
 pass_test!(branches);
+pass_test!(built_mir, "-Lmir_phase=built");
+pass_test!(checked_add, "-Coverflow-checks=on", "-Lpanic_freedom=true");
+pass_test!(division);
+pass_test!(enums);
+pass_test!(ensures, "-Coverflow-checks=on");
+pass_test!(extern_specs);
 pass_test!(fun_call);
+pass_test!(int_ranges);
+pass_test!(lifetimes);
+pass_test!(methods);
+pass_test!(modules);
+pass_test!(mut_refs);
+pass_test!(overflow);
+pass_test!(panic_freedom, "-Lpanic_freedom=true");
+pass_test!(promoted_mir, "-Lmir_phase=promoted");
+pass_test!(selective);
+pass_test!(shared_refs);
 pass_test!(simple);
+pass_test!(spec_files, "-Lspec_files=tests/pass/spec_files.lrspec");
+pass_test!(structs);
+pass_test!(traits);
+pass_test!(tuples);
+pass_test!(unannotated);
 pass_test!(while_loop);
+pass_test!(wrapping, "-Lint_model=wrapping");
+
+/// Check that `extern_crate` verifies against the signatures exported by the library it depends on,
+/// which is verified and compiled first.
+#[test]
+fn extern_crate() {
+    let home = env!("RUSTUP_HOME");
+    let toolchain = env!("RUSTUP_TOOLCHAIN");
+    let sysroot = format!("--sysroot={}/toolchains/{}", home, toolchain);
+
+    let out_dir =
+        std::env::temp_dir().join(format!("liquid-rust-extern-crate-{}", std::process::id()));
+    let lib_code = liquid_rust_driver::run_compiler(vec![
+        "whatever".into(),
+        "tests/pass/extern_crate_lib.rs".into(),
+        sysroot.clone(),
+        "--crate-type=lib".into(),
+        "--emit=metadata".into(),
+        format!("--out-dir={}", out_dir.display()),
+        "-Lcodegen=on_success".into(),
+    ]);
+    let code = liquid_rust_driver::run_compiler(vec![
+        "whatever".into(),
+        "tests/pass/extern_crate.rs".into(),
+        sysroot,
+        "-O".into(),
+        "--crate-type=lib".into(),
+        format!(
+            "--extern=extern_crate_lib={}",
+            out_dir.join("libextern_crate_lib.rmeta").display()
+        ),
+    ]);
+    let _ = std::fs::remove_dir_all(&out_dir);
+    assert!(lib_code == 0);
+    assert!(code == 0);
+}
@@ -0,0 +1,53 @@
+#![feature(register_tool)]
+#![register_tool(liquid)]
+
+pub mod ints {
+    pub struct P {
+        #[liquid::field("{int | v >= 0}")]
+        pub x: i32,
+    }
+
+    #[liquid::ty("fn(p: P) -> {v: int | v >= 0}")]
+    pub fn get(p: P) -> i32 {
+        p.x
+    }
+}
+
+pub mod bools {
+    pub struct P {
+        pub b: bool,
+    }
+
+    #[liquid::ty("fn(p: P) -> bool")]
+    pub fn get(p: P) -> bool {
+        p.b
+    }
+}
+
+#[liquid::ty("fn() -> {v: int | v >= 0}")]
+pub fn get_ints() -> i32 {
+    ints::get(ints::P { x: 1 })
+}
+
+pub mod options {
+    pub enum Option {
+        Nothing,
+        Just(bool),
+    }
+
+    #[liquid::ty("fn(o: Option) -> bool")]
+    pub fn get(o: Option) -> bool {
+        match o {
+            Option::Nothing => false,
+            Option::Just(b) => b,
+        }
+    }
+}
+
+#[liquid::ty("fn(x: Option<{int | v >= 0}>) -> {v: int | v >= 0}")]
+pub fn get_or_zero(x: Option<i32>) -> i32 {
+    match x {
+        Some(n) => n,
+        None => 0,
+    }
+}
@@ -0,0 +1,54 @@
+#![feature(register_tool)]
+#![register_tool(liquid)]
+
+#[liquid::ty("fn(x: &mut {v: int | v >= 0})")]
+pub fn incr(x: &mut i32) {
+    if *x < 100 {
+        *x += 1;
+    }
+}
+
+#[liquid::ty("fn() -> {v: int | v > 0}")]
+pub fn borrow_and_write() -> i32 {
+    let mut x = 0;
+    let r = &mut x;
+    *r = 5;
+    x
+}
+
+#[liquid::ty("fn() -> {v: int | v >= 0}")]
+pub fn call_incr() -> i32 {
+    let mut x = 1;
+    incr(&mut x);
+    incr(&mut x);
+    x
+}
+
+#[liquid::ty("fn(n: {int | n >= 0}) -> {v: int | v >= 0}")]
+pub fn loop_through_ref(n: i32) -> i32 {
+    let mut x = 0;
+    let r = &mut x;
+    let mut i = 0;
+    while i < n {
+        *r = i;
+        i += 1;
+    }
+    x
+}
+
+#[liquid::ty("fn(c: bool) -> {v: int | v > 0}")]
+pub fn write_either(c: bool) -> i32 {
+    let mut x = 0;
+    let mut y = 1;
+    let r = if c { &mut x } else { &mut y };
+    *r = 5;
+    x + y
+}
+
+#[liquid::ty("fn(x: &mut {v: int | v >= 0}) -> {v: int | v >= 0}")]
+pub fn write_arg_or_local(x: &mut i32) -> i32 {
+    let mut y = 0;
+    let r = if *x > 10 { &mut *x } else { &mut y };
+    *r = 7;
+    y
+}
@@ -0,0 +1,24 @@
+#![feature(register_tool)]
+#![register_tool(liquid)]
+
+// Pass tests are compiled with optimizations, so rustc doesn't check arithmetic for overflow and
+// the operations must be proved not to overflow.
+#[liquid::ty("fn(x: {int | x < 100}) -> {v: int | v == x + 1}")]
+pub fn succ(x: i32) -> i32 {
+    x + 1
+}
+
+#[liquid::ty("fn(x: {int | x >= 0}) -> {v: int | v == -x}")]
+pub fn neg(x: i32) -> i32 {
+    -x
+}
+
+#[liquid::ty("fn(x: {int | x < 100}) -> {v: int | v == 2 * x}")]
+pub fn double(x: u8) -> u8 {
+    x * 2
+}
+
+#[liquid::ty("fn(x: int, y: {int | y <= x}) -> {v: int | v >= 0}")]
+pub fn distance(x: u32, y: u32) -> u32 {
+    x - y
+}
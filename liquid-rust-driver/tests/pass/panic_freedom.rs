@@ -0,0 +1,18 @@
+#![feature(register_tool)]
+#![register_tool(liquid)]
+
+#[liquid::ty("fn(x: int, y: {int | y > 0}) -> int")]
+pub fn div(x: u32, y: u32) -> u32 {
+    x / y
+}
+
+#[liquid::ty("fn(x: int, y: {int | y != 0}) -> int")]
+pub fn rem(x: u32, y: u32) -> u32 {
+    x % y
+}
+
+#[liquid::ty("fn(x: int, y: {int | y > 0}) -> int")]
+pub fn signed_div(x: i32, y: i32) -> i32 {
+    // Neither zero nor -1, so the division can't overflow either.
+    x / y
+}
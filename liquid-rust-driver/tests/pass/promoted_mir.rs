@@ -0,0 +1,23 @@
+#![feature(register_tool)]
+#![register_tool(liquid)]
+
+// Const checking steals the promoted MIR of a `const fn` during analysis.
+#[liquid::ty("fn(n: {int | n < 100}) -> {v: int | v > n}")]
+pub const fn succ(n: i32) -> i32 {
+    n + 1
+}
+
+#[liquid::ty("fn(x: &mut {v: int | v >= 0})")]
+pub fn incr(x: &mut i32) {
+    if *x < 100 {
+        *x += 1;
+    }
+}
+
+#[liquid::ty("fn() -> {v: int | v > 0}")]
+pub fn borrow_and_write() -> i32 {
+    let mut x = 0;
+    let r = &mut x;
+    *r = succ(4);
+    x
+}
@@ -0,0 +1,19 @@
+#![feature(register_tool)]
+#![register_tool(liquid)]
+
+#[liquid::ty("fn(b: bool) -> {v: int | v >= 0 && v <= 1}")]
+#[liquid::trusted]
+pub fn to_int(b: bool) -> i32 {
+    b as i32
+}
+
+#[liquid::ty("fn(b: bool) -> {v: int | v >= 0}")]
+pub fn to_nat(b: bool) -> i32 {
+    to_int(b)
+}
+
+#[liquid::ignore]
+#[liquid::ty("fn(b: bool) -> {v: int | v == 1}")]
+pub fn to_int_ignored(b: bool) -> i32 {
+    b as i32
+}
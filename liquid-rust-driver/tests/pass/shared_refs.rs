@@ -0,0 +1,41 @@
+#![feature(register_tool)]
+#![register_tool(liquid)]
+
+#[liquid::ty("fn(x: &{v: int | v > 0}) -> {v: int | v > 0}")]
+pub fn get(x: &i32) -> i32 {
+    *x
+}
+
+#[liquid::ty("fn(x: &{v: int | v > 0}, y: &{v: int | v > 0}) -> {v: int | v > 0}")]
+pub fn max(x: &i32, y: &i32) -> i32 {
+    if *x > *y {
+        *x
+    } else {
+        *y
+    }
+}
+
+#[liquid::ty("fn() -> {v: int | v == 5}")]
+pub fn borrow_local() -> i32 {
+    let x = 5;
+    let r = &x;
+    let s = r;
+    *s
+}
+
+#[liquid::ty("fn() -> {v: int | v > 0}")]
+pub fn call_get() -> i32 {
+    let x = 3;
+    get(&x)
+}
+
+#[liquid::ty("fn(n: {int | n >= 0}) -> {v: int | v >= 0}")]
+pub fn read_in_loop(n: i32) -> i32 {
+    let x = n;
+    let r = &x;
+    let mut i = 0;
+    while i < *r {
+        i += 1;
+    }
+    i
+}
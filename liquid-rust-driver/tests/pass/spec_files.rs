@@ -0,0 +1,7 @@
+#![feature(register_tool)]
+#![register_tool(liquid)]
+
+#[liquid::ty("fn(x: {int | x > 0}) -> {v: int | v == x}")]
+pub fn abs_pos(x: i32) -> i32 {
+    x.abs()
+}
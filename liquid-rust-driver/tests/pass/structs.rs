@@ -0,0 +1,56 @@
+#![feature(register_tool)]
+#![register_tool(liquid)]
+
+pub struct Range {
+    #[liquid::field("{int | v >= 0}")]
+    lo: i32,
+    #[liquid::field("{int | v >= lo}")]
+    hi: i32,
+}
+
+#[liquid::ty("fn(lo: {int | lo >= 0}, hi: {int | hi >= lo}) -> Range")]
+pub fn new_range(lo: i32, hi: i32) -> Range {
+    Range { lo, hi }
+}
+
+#[liquid::ty("fn(r: Range) -> {v: int | v >= 0}")]
+pub fn len(r: Range) -> i32 {
+    r.hi - r.lo
+}
+
+#[liquid::ty("fn(r: &mut Range)")]
+pub fn widen(r: &mut Range) {
+    if r.hi < 100 {
+        r.hi += 1;
+    }
+}
+
+#[liquid::ty("fn() -> {v: int | v >= 0}")]
+pub fn use_range() -> i32 {
+    let mut r = new_range(1, 5);
+    widen(&mut r);
+    r.lo = 0;
+    len(r)
+}
+
+pub struct Counter {
+    #[liquid::field("{count: int | count >= 0}")]
+    count: u32,
+    done: bool,
+}
+
+#[liquid::ty("fn(n: {int | n >= 0}) -> {v: int | v >= 0}")]
+pub fn count_to(n: u32) -> u32 {
+    let mut c = Counter {
+        count: 0,
+        done: false,
+    };
+    while !c.done {
+        if c.count < n {
+            c.count += 1;
+        } else {
+            c.done = true;
+        }
+    }
+    c.count
+}
@@ -0,0 +1,34 @@
+#![feature(register_tool)]
+#![register_tool(liquid)]
+
+pub trait Nat {
+    #[liquid::ty("fn(n: {int | n >= 0 && n < 1000}) -> {v: int | v >= 0 && v <= n + 1}")]
+    fn step(n: i32) -> i32;
+}
+
+pub struct Succ;
+
+impl Nat for Succ {
+    #[liquid::ty("fn(n: {int | n < 2147483647}) -> {v: int | v == n + 1}")]
+    fn step(n: i32) -> i32 {
+        n + 1
+    }
+}
+
+pub struct Zero;
+
+impl Nat for Zero {
+    fn step(_n: i32) -> i32 {
+        0
+    }
+}
+
+#[liquid::ty("fn(n: {int | n >= 0 && n < 999}) -> {v: int | v >= 0}")]
+pub fn step_twice<T: Nat>(n: i32) -> i32 {
+    T::step(T::step(n))
+}
+
+#[liquid::ty("fn(n: {int | n >= 0 && n < 2147483647}) -> {v: int | v == n + 1}")]
+pub fn succ(n: i32) -> i32 {
+    Succ::step(n)
+}
@@ -0,0 +1,39 @@
+#![feature(register_tool)]
+#![register_tool(liquid)]
+
+#[liquid::ty("fn(x: {int | x >= 0}, y: {int | y > x}) -> (a: {int | a >= 0}, {b: int | b > a})")]
+pub fn pair(x: i32, y: i32) -> (i32, i32) {
+    (x, y)
+}
+
+#[liquid::ty("fn(p: (a: {int | a >= 0}, {b: int | b > a})) -> {v: int | v > 0}")]
+pub fn diff(p: (i32, i32)) -> i32 {
+    p.1 - p.0
+}
+
+#[liquid::ty("fn(x: int) -> (a: int, {b: int | b == a})")]
+pub fn dup(x: i32) -> (i32, i32) {
+    let mut p = (x, 0);
+    p.1 = x;
+    p
+}
+
+#[liquid::ty("fn(x: {int | x >= 0}, y: {int | y > x}) -> {v: int | v > 0}")]
+pub fn call(x: i32, y: i32) -> i32 {
+    diff(pair(x, y))
+}
+
+#[liquid::ty("fn(p: (a: {int | a >= 0}, {b: int | b < 1000})) -> {v: int | v == p.0 + p.1}")]
+pub fn sum(p: (u32, u32)) -> u32 {
+    p.0 + p.1
+}
+
+#[liquid::ty("fn(p: (a: int, b: int)) -> (c: {int | c == p.1}, {d: int | d == p.0})")]
+pub fn swap(p: (i32, i32)) -> (i32, i32) {
+    (p.1, p.0)
+}
+
+#[liquid::ty("fn(p: (a: int, b: int), x: {int | x == p.1}) -> {v: int | v == p.1}")]
+pub fn second(_p: (i32, i32), x: i32) -> i32 {
+    x
+}
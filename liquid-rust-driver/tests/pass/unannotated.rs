@@ -0,0 +1,26 @@
+#![feature(register_tool)]
+#![register_tool(liquid)]
+
+pub fn double(x: i32) -> i32 {
+    x * 2
+}
+
+#[liquid::ty("fn(x: int) -> {v: int | v >= 0}")]
+pub fn double_or_zero(x: i32) -> i32 {
+    let y = double(x);
+    if y >= 0 {
+        y
+    } else {
+        0
+    }
+}
+
+#[liquid::ty("fn(x: int, y: int) -> {v: int | v >= 0}")]
+pub fn max_or_zero(x: i32, y: i32) -> i32 {
+    let z = std::cmp::max(x, y);
+    if z >= 0 {
+        z
+    } else {
+        0
+    }
+}
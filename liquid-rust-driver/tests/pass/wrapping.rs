@@ -0,0 +1,18 @@
+#![feature(register_tool)]
+#![register_tool(liquid)]
+
+// Arithmetic that isn't checked for overflow wraps around in the wrapping integer model.
+#[liquid::ty("fn(x: {int | x == 255}) -> {v: int | v == 0}")]
+pub fn succ_max(x: u8) -> u8 {
+    x + 1
+}
+
+#[liquid::ty("fn(x: {int | x == 2147483647}) -> {v: int | v == -2147483648}")]
+pub fn signed_succ_max(x: i32) -> i32 {
+    x + 1
+}
+
+#[liquid::ty("fn(x: int) -> {v: int | v >= 0 && v <= 255}")]
+pub fn double(x: u8) -> u8 {
+    x * 2
+}
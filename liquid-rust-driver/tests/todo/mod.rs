@@ -1,7 +1,6 @@
 macro_rules! todo_test {
     ($name:ident) => {
         #[test]
-        #[should_panic]
         fn $name() {
             let home = env!("RUSTUP_HOME");
             let toolchain = env!("RUSTUP_TOOLCHAIN");
@@ -9,13 +8,14 @@ macro_rules! todo_test {
 
             let path = concat!("tests/todo/", stringify!($name), ".rs");
 
-            liquid_rust_driver::run_compiler(vec![
+            let code = liquid_rust_driver::run_compiler(vec![
                 "whatever".into(),
-                "-Znll-facts".into(),
                 path.into(),
                 sysroot.into(),
                 "--crate-type=lib".into(),
             ]);
+            // Unsupported features are reported as errors.
+            assert!(code != 0);
         }
     };
 }
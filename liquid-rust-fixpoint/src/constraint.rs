@@ -7,6 +7,8 @@ use std::{collections::HashMap, fmt};
 
 pub enum Constraint {
     Pred(Pred),
+    /// A predicate with a tag identifying it in the result if it doesn't hold.
+    Tagged(Pred, String),
     Conj(Vec<Self>),
     Guard(Pred, Box<Self>),
     ForAll(Sort, Pred, Box<Self>),
@@ -28,6 +30,10 @@ impl Emit for Constraint {
     fn emit<W: fmt::Write>(&self, w: &mut W, ctx: &Ctx) -> fmt::Result {
         match self {
             Self::Pred(pred) => emit!(w, ctx, "({})", pred),
+            Self::Tagged(pred, tag) => {
+                emit!(w, ctx, "(tag ({}) ", pred)?;
+                write!(w, "{:?})", tag)
+            }
             Self::Conj(preds) => {
                 emit!(w, ctx, "(and")?;
                 for pred in preds {
@@ -115,11 +121,13 @@ impl Emit for Pred {
     }
 }
 
+#[derive(Clone)]
 pub enum Expr {
     Variable(usize),
     Constant(Constant),
     BinaryOp(BinOp, Box<Self>, Box<Self>),
     UnaryOp(UnOp, Box<Self>),
+    IfThenElse(Box<Self>, Box<Self>, Box<Self>),
 }
 
 impl Emit for Expr {
@@ -136,6 +144,10 @@ impl Emit for Expr {
         match self {
             Self::Variable(index) => write!(w, "v{}", index),
             Self::Constant(constant) => write!(w, "{}", constant),
+            // Operators without a counterpart in fixpoint are applied as uninterpreted functions.
+            Self::BinaryOp(bin_op, op1, op2) if bin_op.is_uninterpreted() => {
+                emit!(w, ctx, "({} ({}) ({}))", bin_op, op1, op2)
+            }
             Self::BinaryOp(bin_op, op1, op2) => {
                 if should_parenthesize(*bin_op, op1) {
                     emit!(w, ctx, "({})", op1)?;
@@ -157,6 +169,9 @@ impl Emit for Expr {
                     emit!(w, ctx, "{}({})", un_op, op)
                 }
             }
+            Self::IfThenElse(cond, then, els) => {
+                emit!(w, ctx, "(if {} then {} else {})", cond, then, els)
+            }
         }
     }
 }
@@ -236,7 +251,7 @@ impl KVarGatherCtx {
 impl Constraint {
     fn gather_kvars(&self, cx: &mut KVarGatherCtx) {
         match self {
-            Constraint::Pred(pred) => pred.gather_kvars(cx),
+            Constraint::Pred(pred) | Constraint::Tagged(pred, _) => pred.gather_kvars(cx),
             Constraint::Conj(constraints) => {
                 for c in constraints {
                     c.gather_kvars(cx);
@@ -285,10 +300,14 @@ pub enum BinOp {
     Sub,
     /// The integer multiplication operator.
     Mul,
-    /// The `/` operator.
+    /// The `/` operator. Rust's division truncates toward zero while fixpoint's division is
+    /// Euclidean, i.e., its remainder is never negative. Refinements are embedded accordingly.
     Div,
-    /// The `%` operator.
+    /// The `%` operator. Rust's remainder has the sign of the dividend while fixpoint's is
+    /// Euclidean. Refinements are embedded accordingly.
     Rem,
+    /// The Euclidean remainder operator, which is never negative.
+    Mod,
     /// The `&&` operator.
     Eq,
     /// The "not equal to" operator for a particular base type.
@@ -305,13 +324,27 @@ pub enum BinOp {
     And,
     /// The boolean "or" operator.
     Or,
+    /// The bitwise "and" operator.
+    BitAnd,
+    /// The bitwise "or" operator.
+    BitOr,
+    /// The bitwise "exclusive or" operator.
+    BitXor,
+    /// The left shift operator.
+    Shl,
+    /// The right shift operator.
+    Shr,
 }
 
 impl BinOp {
     pub fn precedence(&self) -> u32 {
         match self {
-            BinOp::Mul | BinOp::Div | BinOp::Rem => 5,
-            BinOp::Add | BinOp::Sub => 4,
+            BinOp::Mul | BinOp::Div | BinOp::Rem | BinOp::Mod => 9,
+            BinOp::Add | BinOp::Sub => 8,
+            BinOp::Shl | BinOp::Shr => 7,
+            BinOp::BitAnd => 6,
+            BinOp::BitXor => 5,
+            BinOp::BitOr => 4,
             BinOp::Eq | BinOp::Neq | BinOp::Lt | BinOp::Gt | BinOp::Lte | BinOp::Gte => 3,
             BinOp::And => 2,
             BinOp::Or => 1,
@@ -319,7 +352,21 @@ impl BinOp {
     }
 
     pub fn associative(precedence: u32) -> bool {
-        precedence != 3
+        precedence != 3 && precedence != 7
+    }
+
+    /// The operators fixpoint doesn't interpret. They are declared as uninterpreted functions on
+    /// integers, so only the facts known about their results can be used to prove anything.
+    pub const UNINTERPRETED: [BinOp; 5] = [
+        BinOp::BitAnd,
+        BinOp::BitOr,
+        BinOp::BitXor,
+        BinOp::Shl,
+        BinOp::Shr,
+    ];
+
+    pub fn is_uninterpreted(&self) -> bool {
+        Self::UNINTERPRETED.contains(self)
     }
 }
 
@@ -332,6 +379,7 @@ impl fmt::Display for BinOp {
             BinOp::Mul => write!(f, "*"),
             BinOp::Div => write!(f, "/"),
             BinOp::Rem => write!(f, "%"),
+            BinOp::Mod => write!(f, "mod"),
             BinOp::Neq => write!(f, "!="),
             BinOp::Lt => write!(f, "<"),
             BinOp::Gt => write!(f, ">"),
@@ -339,6 +387,11 @@ impl fmt::Display for BinOp {
             BinOp::Gte => write!(f, ">="),
             BinOp::And => write!(f, "&&"),
             BinOp::Or => write!(f, "||"),
+            BinOp::BitAnd => write!(f, "&"),
+            BinOp::BitOr => write!(f, "|"),
+            BinOp::BitXor => write!(f, "^"),
+            BinOp::Shl => write!(f, "<<"),
+            BinOp::Shr => write!(f, ">>"),
         }
     }
 }
@@ -346,7 +399,12 @@ impl fmt::Display for BinOp {
 impl Emit for BinOp {
     fn emit<W: fmt::Write>(&self, w: &mut W, _ctx: &Ctx) -> fmt::Result {
         match self {
-            BinOp::Rem => write!(w, "mod"),
+            BinOp::Rem | BinOp::Mod => write!(w, "mod"),
+            BinOp::BitAnd => write!(w, "bitand"),
+            BinOp::BitOr => write!(w, "bitor"),
+            BinOp::BitXor => write!(w, "bitxor"),
+            BinOp::Shl => write!(w, "shl"),
+            BinOp::Shr => write!(w, "shr"),
             _ => write!(w, "{}", self),
         }
     }
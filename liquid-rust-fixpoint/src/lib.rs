@@ -91,12 +91,44 @@ fn emit_preamble<W: std::io::Write>(w: &mut W) -> std::io::Result<()> {
     for qualif in qualifiers.iter() {
         emit!(w, &0, "{}", qualif)?;
     }
+    for bin_op in BinOp::UNINTERPRETED.iter() {
+        emit!(w, &0, "(constant {} (func(0, [int; int; int])))", bin_op)?;
+    }
     Ok(())
 }
 
 #[derive(Deserialize, Debug)]
 pub struct FixpointResult {
     pub tag: Safeness,
+    /// Details of the result, e.g., the constraints that failed for an unsafe result.
+    #[serde(default)]
+    pub contents: serde_json::Value,
+}
+
+impl FixpointResult {
+    /// The tags of the failing [tagged](Constraint::Tagged) constraints, if the result is unsafe.
+    pub fn failed_tags(&self) -> Vec<String> {
+        fn gather(value: &serde_json::Value, tags: &mut Vec<String>) {
+            match value {
+                serde_json::Value::Object(object) => {
+                    if let Some(serde_json::Value::String(tag)) = object.get("tag") {
+                        tags.push(tag.clone());
+                    }
+                    object.values().for_each(|value| gather(value, tags));
+                }
+                serde_json::Value::Array(values) => {
+                    values.iter().for_each(|value| gather(value, tags));
+                }
+                _ => {}
+            }
+        }
+
+        let mut tags = Vec::new();
+        if self.tag == Safeness::Unsafe {
+            gather(&self.contents, &mut tags);
+        }
+        tags
+    }
 }
 
 #[derive(Deserialize, Eq, PartialEq, Debug)]
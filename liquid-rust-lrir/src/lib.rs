@@ -7,6 +7,7 @@ extern crate rustc_middle;
 extern crate rustc_mir;
 extern crate rustc_serialize;
 extern crate rustc_span;
+extern crate rustc_target;
 
 pub mod mir;
 pub mod ty;
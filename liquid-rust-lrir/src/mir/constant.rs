@@ -48,20 +48,16 @@ impl From<bool> for Constant {
     }
 }
 
-impl From<u128> for Constant {
-    fn from(i: u128) -> Self {
-        Self {
-            bits: i,
-            ty: BaseTy::Int,
-        }
-    }
-}
-
 impl fmt::Display for Constant {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self.ty {
             BaseTy::Bool => write!(f, "{}", self.bits != 0),
-            BaseTy::Int => write!(f, "{}", self.bits),
+            BaseTy::Int(int_ty) if int_ty.is_signed() => {
+                // Sign-extend the bits to the width of an `i128`.
+                let shift = 128 - int_ty.bit_width();
+                write!(f, "{}", ((self.bits << shift) as i128) >> shift)
+            }
+            BaseTy::Int(_) | BaseTy::AnyInt => write!(f, "{}", self.bits),
         }
     }
 }
@@ -15,8 +15,8 @@ pub use local::{Local, LocalDecl};
 pub use operand::Operand;
 pub use place::{Place, PlaceElem, PlaceRef};
 pub use rustc_middle::mir::{BinOp, UnOp};
-pub use rvalue::Rvalue;
+pub use rvalue::{AggregateKind, Rvalue};
 pub use statement::{Statement, StatementKind};
-pub use terminator::{SwitchTargets, Terminator, TerminatorKind};
+pub use terminator::{AssertKind, SwitchTargets, Terminator, TerminatorKind};
 
 pub use rustc_span::Span;
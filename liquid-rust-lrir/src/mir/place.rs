@@ -3,7 +3,7 @@ use crate::mir::Local;
 use std::fmt;
 
 /// A path to a value starting from a [Local].
-#[derive(Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Place {
     pub local: Local,
     pub projection: Vec<PlaceElem>,
@@ -30,6 +30,9 @@ pub enum PlaceElem {
     Field(usize),
     /// A dereference: `*p`.
     Deref,
+    /// A projection into the fields of a variant of an enum, by index: the `as Some` in
+    /// `(p as Some).0`. The enum must be that variant.
+    Downcast(usize),
 }
 
 #[derive(Clone, Copy)]
@@ -74,6 +77,10 @@ impl<'a> fmt::Display for PlaceRef<'a> {
                     s = format!("*{}", s);
                     need_parens = true;
                 }
+                PlaceElem::Downcast(variant) => {
+                    s = format!("({} as {})", s, variant);
+                    need_parens = false;
+                }
             }
         }
         write!(f, "{}", s)
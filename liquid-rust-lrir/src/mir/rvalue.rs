@@ -1,11 +1,29 @@
+use rustc_hir::def_id::DefId;
+
 use crate::{
     mir::{BinOp, Operand, Place, UnOp},
-    ty::{BorrowKind, Region},
+    ty::{BaseTy, BorrowKind, Region},
 };
 
 pub enum Rvalue {
     Use(Operand),
     Ref(Region, BorrowKind, Place),
     BinaryOp(BinOp, Operand, Operand),
+    /// A binary operation that also returns whether it overflowed, as a `(result, overflowed)`
+    /// tuple.
+    CheckedBinaryOp(BinOp, Operand, Operand),
     UnaryOp(UnOp, Operand),
+    /// The discriminant of the enum at the place, an integer of the given type.
+    Discriminant(Place, BaseTy),
+    /// A value built from its fields.
+    Aggregate(AggregateKind, Vec<Operand>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AggregateKind {
+    Tuple,
+    /// A struct.
+    Adt(DefId),
+    /// A variant of an enum, by index.
+    Variant(usize),
 }
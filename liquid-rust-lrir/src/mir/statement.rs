@@ -7,6 +7,9 @@ pub struct Statement {
 
 pub enum StatementKind {
     Assign(Place, Rvalue),
+    /// Makes the enum at the place the variant with the given index. The fields of the variant
+    /// are written before.
+    SetDiscriminant(Place, usize),
     StorageLive(Local),
     StorageDead(Local),
     Nop,
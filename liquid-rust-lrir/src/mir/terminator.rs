@@ -21,6 +21,10 @@ pub enum TerminatorKind<'tcx> {
         targets: SwitchTargets,
     },
     Return,
+    /// Marks code that is never executed, e.g., the code after an infinite loop.
+    Unreachable,
+    /// Continues unwinding. This only terminates cleanup blocks, which are never checked.
+    Resume,
     Call {
         func: (DefId, SubstsRef<'tcx>),
         args: Vec<Operand>,
@@ -30,9 +34,31 @@ pub enum TerminatorKind<'tcx> {
         cond: Operand,
         expected: bool,
         target: BasicBlock,
+        kind: AssertKind,
     },
 }
 
+/// The kind of runtime check performed by an [Assert](TerminatorKind::Assert).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AssertKind {
+    BoundsCheck,
+    Overflow,
+    DivisionByZero,
+    RemainderByZero,
+}
+
+impl AssertKind {
+    /// Describes the panic this check guards against.
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::BoundsCheck => "possible out-of-bounds access",
+            Self::Overflow => "possible arithmetic overflow",
+            Self::DivisionByZero => "possible division by zero",
+            Self::RemainderByZero => "possible remainder by zero",
+        }
+    }
+}
+
 pub struct SwitchTargets {
     values: Vec<u128>,
     targets: Vec<BasicBlock>,
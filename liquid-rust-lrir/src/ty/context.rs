@@ -1,11 +1,13 @@
-use std::cell::RefCell;
+use std::{cell::RefCell, collections::HashMap};
 
 use crate::ty::{
-    BaseTy, BinOp, BorrowKind, Constant, GhostVar, Path, Pred, PredKind, PredS, Refine, Region,
-    Tuple, Ty, TyKind, TyS, UnOp, Var,
+    AdtDef, AdtDefS, BaseTy, BinOp, BorrowKind, Constant, EnumDef, EnumDefS, Field, GhostVar,
+    IntTy, Path, Pred, PredKind, PredS, Refine, Region, Tuple, Ty, TyKind, TyS, UnOp, Var,
+    VariantField,
 };
 
 use hashconsing::{HConsign, HashConsign};
+use rustc_hir::def_id::DefId;
 
 /// Type context used to allocate types.
 ///
@@ -13,6 +15,10 @@ use hashconsing::{HConsign, HashConsign};
 /// [hashconsing]. [TyCtxt] is the main data structure used to allocate and work with types.
 pub struct TyCtxt {
     interner: RefCell<Interner>,
+    /// The structs of the crate whose values can be refined.
+    adt_defs: RefCell<HashMap<DefId, AdtDef>>,
+    /// The enums whose variants can be refined.
+    enum_defs: RefCell<HashMap<DefId, EnumDef>>,
     pub preds: CommonPreds,
     pub types: CommonTypes,
 }
@@ -24,6 +30,8 @@ impl TyCtxt {
         let types = CommonTypes::new(&mut interner, &preds);
         TyCtxt {
             interner: RefCell::new(interner),
+            adt_defs: RefCell::new(HashMap::new()),
+            enum_defs: RefCell::new(HashMap::new()),
             preds,
             types,
         }
@@ -37,12 +45,107 @@ impl TyCtxt {
         self.interner.borrow_mut().intern_pred(kind)
     }
 
+    // Structs
+
+    /// Register the definition of a struct, making it available through [adt_def](Self::adt_def).
+    pub fn register_adt_def(&self, adt_def: AdtDefS) -> AdtDef {
+        let adt_def = self.interner.borrow_mut().intern_adt_def(adt_def);
+        self.adt_defs
+            .borrow_mut()
+            .insert(adt_def.def_id, adt_def.clone());
+        adt_def
+    }
+
+    /// The definition of the struct `def_id`, if it was registered.
+    pub fn adt_def(&self, def_id: DefId) -> Option<AdtDef> {
+        self.adt_defs.borrow().get(&def_id).cloned()
+    }
+
+    /// The definitions of the structs named `name`, ordered by their `DefId`.
+    pub fn adt_defs_by_name(&self, name: &str) -> Vec<AdtDef> {
+        let mut adt_defs = self
+            .adt_defs
+            .borrow()
+            .values()
+            .filter(|adt_def| adt_def.name == name)
+            .cloned()
+            .collect::<Vec<_>>();
+        adt_defs.sort_by_key(|adt_def| adt_def.def_id);
+        adt_defs
+    }
+
+    // Enums
+
+    /// Register the definition of an enum, making it available through
+    /// [enum_def](Self::enum_def).
+    pub fn register_enum_def(&self, enum_def: EnumDefS) -> EnumDef {
+        let enum_def = self.interner.borrow_mut().intern_enum_def(enum_def);
+        self.enum_defs
+            .borrow_mut()
+            .insert(enum_def.def_id, enum_def.clone());
+        enum_def
+    }
+
+    /// The definition of the enum `def_id`, if it was registered.
+    pub fn enum_def(&self, def_id: DefId) -> Option<EnumDef> {
+        self.enum_defs.borrow().get(&def_id).cloned()
+    }
+
+    /// The definitions of the enums named `name`, ordered by their `DefId`.
+    pub fn enum_defs_by_name(&self, name: &str) -> Vec<EnumDef> {
+        let mut enum_defs = self
+            .enum_defs
+            .borrow()
+            .values()
+            .filter(|enum_def| enum_def.name == name)
+            .cloned()
+            .collect::<Vec<_>>();
+        enum_defs.sort_by_key(|enum_def| enum_def.def_id);
+        enum_defs
+    }
+
     // Types
 
     pub fn mk_tuple(&self, tup: Tuple) -> Ty {
         self.mk_ty(TyKind::Tuple(tup))
     }
 
+    pub fn mk_adt(&self, adt_def: AdtDef, tup: Tuple) -> Ty {
+        self.mk_ty(TyKind::Adt(adt_def, tup))
+    }
+
+    /// The type of the values of a struct, i.e., its fields have the types in its definition.
+    pub fn mk_adt_invariant(&self, adt_def: &AdtDef) -> Ty {
+        self.mk_adt(adt_def.clone(), adt_def.fields.clone())
+    }
+
+    pub fn mk_enum(&self, enum_def: EnumDef, variant: Option<usize>, variants: Vec<Ty>) -> Ty {
+        self.mk_ty(TyKind::Enum(enum_def, variant, variants))
+    }
+
+    /// The types of the variants of an enum whose type parameters are instantiated with `args`.
+    pub fn mk_enum_variants(&self, enum_def: &EnumDef, args: &[Ty]) -> Vec<Ty> {
+        enum_def
+            .variants
+            .iter()
+            .map(|variant_def| {
+                let tup = variant_def
+                    .fields
+                    .iter()
+                    .enumerate()
+                    .map(|(i, field)| {
+                        let ty = match field {
+                            VariantField::Param(param) => args[*param].clone(),
+                            VariantField::Base(bty) => self.mk_refine(*bty, self.preds.tt()),
+                        };
+                        (Field::from_usize(i), ty)
+                    })
+                    .collect();
+                self.mk_tuple(tup)
+            })
+            .collect()
+    }
+
     pub fn mk_uninit(&self, n: usize) -> Ty {
         self.mk_ty(TyKind::Uninit(n))
     }
@@ -65,16 +168,88 @@ impl TyCtxt {
                 let tup = tup.map(|i, fld, ty| (*fld, self.selfify(ty, path.extend(i))));
                 self.mk_tuple(tup)
             }
+            TyKind::Adt(adt_def, tup) => {
+                let tup = tup.map(|i, fld, ty| (*fld, self.selfify(ty, path.extend(i))));
+                self.mk_adt(adt_def.clone(), tup)
+            }
+            // Only the fields of the variant the enum is known to be are bound.
+            TyKind::Enum(enum_def, Some(variant), variants) => {
+                let mut variants = variants.clone();
+                variants[*variant] = self.selfify(&variants[*variant], path.extend(*variant));
+                self.mk_enum(enum_def.clone(), Some(*variant), variants)
+            }
+            _ => ty.clone(),
+        }
+    }
+
+    /// Like [selfify](Self::selfify), but the value of `ty` is the one at either `path1` or
+    /// `path2`.
+    pub fn selfify_either(&self, ty: &Ty, path1: Path, path2: Path) -> Ty {
+        match ty.kind() {
+            TyKind::Refined(bty, _) => {
+                let pred = self.mk_bin_op(
+                    BinOp::Or,
+                    self.mk_bin_op(BinOp::Eq, self.preds.nu(), self.mk_path(path1)),
+                    self.mk_bin_op(BinOp::Eq, self.preds.nu(), self.mk_path(path2)),
+                );
+                self.mk_refine(*bty, pred)
+            }
+            TyKind::Tuple(tup) => {
+                let tup = tup.map(|i, fld, ty| {
+                    (
+                        *fld,
+                        self.selfify_either(ty, path1.extend(i), path2.extend(i)),
+                    )
+                });
+                self.mk_tuple(tup)
+            }
+            TyKind::Adt(adt_def, tup) => {
+                let tup = tup.map(|i, fld, ty| {
+                    (
+                        *fld,
+                        self.selfify_either(ty, path1.extend(i), path2.extend(i)),
+                    )
+                });
+                self.mk_adt(adt_def.clone(), tup)
+            }
+            // The values may be of different variants, so nothing is known about the fields.
+            TyKind::Enum(..) => self.forget(ty),
             _ => ty.clone(),
         }
     }
 
+    /// Returns a type with the shape of `ty` where every refinement is trivially true, except for
+    /// the invariants of structs.
+    pub fn forget(&self, ty: &Ty) -> Ty {
+        match ty.kind() {
+            TyKind::Refined(bty, _) => self.mk_refine(*bty, self.preds.tt()),
+            TyKind::Tuple(tup) => {
+                let tup = tup.map(|_, fld, ty| (*fld, self.forget(ty)));
+                self.mk_tuple(tup)
+            }
+            TyKind::Adt(adt_def, _) => self.mk_adt_invariant(adt_def),
+            TyKind::Enum(enum_def, _, variants) => {
+                let variants = variants.iter().map(|ty| self.forget(ty)).collect();
+                self.mk_enum(enum_def.clone(), None, variants)
+            }
+            TyKind::Ref(..) | TyKind::Uninit(_) => ty.clone(),
+        }
+    }
+
     pub fn uninitialize(&self, ty: &Ty) -> Ty {
         match ty.kind() {
             TyKind::Tuple(tup) => {
                 let tup = tup.map(|_, fld, ty| (*fld, self.uninitialize(ty)));
                 self.mk_tuple(tup)
             }
+            TyKind::Adt(adt_def, tup) => {
+                let tup = tup.map(|_, fld, ty| (*fld, self.uninitialize(ty)));
+                self.mk_adt(adt_def.clone(), tup)
+            }
+            TyKind::Enum(enum_def, _, variants) => {
+                let variants = variants.iter().map(|ty| self.uninitialize(ty)).collect();
+                self.mk_enum(enum_def.clone(), None, variants)
+            }
             _ => self.mk_uninit(ty.size()),
         }
     }
@@ -86,11 +261,142 @@ impl TyCtxt {
     }
 
     pub fn mk_const_from_bits(&self, bits: u128, ty: BaseTy) -> Pred {
-        let c = match ty {
-            BaseTy::Int => Constant::Int(bits),
-            BaseTy::Bool => Constant::Bool(bits != 0),
+        match ty {
+            // Signed integers are in two's complement.
+            BaseTy::Int(int_ty) if int_ty.is_signed() && bits >> (int_ty.bit_width() - 1) == 1 => {
+                let mask = u128::MAX >> (128 - int_ty.bit_width());
+                self.mk_int(true, bits.wrapping_neg() & mask)
+            }
+            BaseTy::Int(_) | BaseTy::AnyInt => self.mk_const(Constant::Int(bits)),
+            BaseTy::Bool => self.mk_const(Constant::Bool(bits != 0)),
+        }
+    }
+
+    /// An integer constant with absolute value `abs`, which is negative if `negative` is true.
+    pub fn mk_int(&self, negative: bool, abs: u128) -> Pred {
+        let c = self.mk_const(Constant::Int(abs));
+        if negative && abs != 0 {
+            self.mk_un_op(UnOp::Neg, c)
+        } else {
+            c
+        }
+    }
+
+    /// The predicate `min <= pred && pred <= max`, where `min` and `max` are the bounds of
+    /// `int_ty`.
+    pub fn mk_in_range(&self, int_ty: IntTy, pred: Pred) -> Pred {
+        let min = self.mk_int(int_ty.is_signed(), int_ty.min_abs());
+        let max = self.mk_const(Constant::Int(int_ty.max()));
+        self.mk_bin_op(
+            BinOp::And,
+            self.mk_bin_op(BinOp::Gte, pred.clone(), min),
+            self.mk_bin_op(BinOp::Lte, pred, max),
+        )
+    }
+
+    /// The result of wrapping `pred` around the range of `int_ty`, i.e., the result of an
+    /// arithmetic operation with wrapping semantics.
+    pub fn mk_wrapping(&self, int_ty: IntTy, pred: Pred) -> Pred {
+        let width = int_ty.bit_width();
+        // 2^128 doesn't fit in a constant.
+        let modulus = if width < 128 {
+            self.mk_const(Constant::Int(1 << width))
+        } else {
+            let half = self.mk_const(Constant::Int(1 << 64));
+            self.mk_bin_op(BinOp::Mul, half.clone(), half)
+        };
+
+        if int_ty.is_signed() {
+            // Shift the range to start at zero and back.
+            let offset = self.mk_const(Constant::Int(int_ty.min_abs()));
+            let shifted = self.mk_bin_op(BinOp::Add, pred, offset.clone());
+            self.mk_bin_op(
+                BinOp::Sub,
+                self.mk_bin_op(BinOp::Mod, shifted, modulus),
+                offset,
+            )
+        } else {
+            self.mk_bin_op(BinOp::Mod, pred, modulus)
+        }
+    }
+
+    /// The refinement of the result `v` of a bitwise or shift operation on integers of kind
+    /// `int_ty`. Fixpoint doesn't interpret these operators, so `v = op1 bin_op op2` is
+    /// strengthened with facts about `v`: bounds for bitwise operators on non-negative operands,
+    /// and its exact value for shifts by a constant amount.
+    pub fn mk_bitwise_result(&self, bin_op: BinOp, int_ty: IntTy, op1: Pred, op2: Pred) -> Pred {
+        let nu = self.preds.nu();
+        let zero = self.mk_const(Constant::Int(0));
+        let negative = |op: &Pred| self.mk_bin_op(BinOp::Lt, op.clone(), zero.clone());
+        // `fact` holds unless `exception` does.
+        let unless = |exception: Pred, fact: Pred| self.mk_bin_op(BinOp::Or, exception, fact);
+        let between = |low: Pred, high: Pred| {
+            self.mk_bin_op(
+                BinOp::And,
+                self.mk_bin_op(BinOp::Lte, low, nu.clone()),
+                self.mk_bin_op(BinOp::Lte, nu.clone(), high),
+            )
+        };
+        // The shift amount is masked by the width of the type.
+        let shift_amount = match op2.kind() {
+            PredKind::Const(Constant::Int(amount)) => {
+                Some(self.mk_const(Constant::Int(1 << (amount % int_ty.bit_width() as u128))))
+            }
+            _ => None,
         };
-        self.mk_const(c)
+
+        let mut facts = vec![self.mk_bin_op(
+            BinOp::Eq,
+            nu.clone(),
+            self.mk_bin_op(bin_op, op1.clone(), op2.clone()),
+        )];
+        match bin_op {
+            // `0 <= op1 & op2 <= op` if either operand `op` is non-negative.
+            BinOp::BitAnd => {
+                facts.push(unless(negative(&op1), between(zero.clone(), op1.clone())));
+                facts.push(unless(negative(&op2), between(zero, op2)));
+            }
+            // `max(op1, op2) <= op1 | op2 <= op1 + op2` if both operands are non-negative.
+            BinOp::BitOr => facts.push(unless(
+                self.mk_bin_op(BinOp::Or, negative(&op1), negative(&op2)),
+                self.mk_bin_op(
+                    BinOp::And,
+                    self.mk_bin_op(BinOp::Gte, nu.clone(), op1.clone()),
+                    between(op2.clone(), self.mk_bin_op(BinOp::Add, op1, op2)),
+                ),
+            )),
+            // `0 <= op1 ^ op2 <= op1 + op2` if both operands are non-negative.
+            BinOp::BitXor => facts.push(unless(
+                self.mk_bin_op(BinOp::Or, negative(&op1), negative(&op2)),
+                between(zero, self.mk_bin_op(BinOp::Add, op1, op2)),
+            )),
+            // The bits shifted out are dropped, so `op1 << k` is `op1 * 2^k` wrapped around.
+            BinOp::Shl => {
+                if let Some(factor) = shift_amount {
+                    let value = self.mk_wrapping(int_ty, self.mk_bin_op(BinOp::Mul, op1, factor));
+                    facts.push(self.mk_bin_op(BinOp::Eq, nu, value));
+                }
+            }
+            // `op1 >> k` is `op1 / 2^k` rounded down, i.e., `v * 2^k <= op1 < (v + 1) * 2^k`.
+            BinOp::Shr => match shift_amount {
+                Some(factor) => {
+                    let scaled = self.mk_bin_op(BinOp::Mul, nu, factor.clone());
+                    facts.push(self.mk_bin_op(BinOp::Lte, scaled.clone(), op1.clone()));
+                    facts.push(self.mk_bin_op(
+                        BinOp::Lt,
+                        op1,
+                        self.mk_bin_op(BinOp::Add, scaled, factor),
+                    ));
+                }
+                None => facts.push(unless(negative(&op1), between(zero, op1))),
+            },
+            _ => unreachable!("not a bitwise operator: {}", bin_op),
+        }
+
+        facts
+            .into_iter()
+            .reduce(|p1, p2| self.mk_bin_op(BinOp::And, p1, p2))
+            .unwrap()
     }
 
     pub fn mk_path<P: Into<Path>>(&self, path: P) -> Pred {
@@ -124,7 +430,7 @@ impl CommonTypes {
         let mut intern = |typ| interner.intern_ty(typ);
         let mk_refine = |bty| TyKind::Refined(bty, Refine::Pred(preds.tt()));
         CommonTypes {
-            int: intern(mk_refine(BaseTy::Int)),
+            int: intern(mk_refine(BaseTy::AnyInt)),
             bool: intern(mk_refine(BaseTy::Bool)),
             unit: intern(TyKind::Tuple(Tuple::empty())),
         }
@@ -179,6 +485,8 @@ impl CommonPreds {
 struct Interner {
     types: HConsign<TyS>,
     preds: HConsign<PredS>,
+    adt_defs: HConsign<AdtDefS>,
+    enum_defs: HConsign<EnumDefS>,
 }
 
 impl Interner {
@@ -186,6 +494,8 @@ impl Interner {
         Interner {
             types: HConsign::empty(),
             preds: HConsign::empty(),
+            adt_defs: HConsign::empty(),
+            enum_defs: HConsign::empty(),
         }
     }
 
@@ -196,4 +506,12 @@ impl Interner {
     fn intern_pred(&mut self, kind: PredKind) -> Pred {
         self.preds.mk(PredS { kind })
     }
+
+    fn intern_adt_def(&mut self, adt_def: AdtDefS) -> AdtDef {
+        self.adt_defs.mk(adt_def)
+    }
+
+    fn intern_enum_def(&mut self, enum_def: EnumDefS) -> EnumDef {
+        self.enum_defs.mk(enum_def)
+    }
 }
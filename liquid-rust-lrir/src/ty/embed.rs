@@ -1,29 +1,78 @@
-use super::{BaseTy, KVid, PredKind, PredS, Refine, Var};
+use super::{BaseTy, BinOp, Field, IntTy, KVid, Path, PredKind, PredS, Refine, Var};
 use liquid_rust_fixpoint as fixpoint;
 
 #[derive(Default)]
 pub struct EmbeddingCtxt {
-    scope: Vec<Var>,
+    /// The paths bound in the current scope. Tuples are flattened, so only paths to values of a
+    /// [base type](BaseTy) are bound.
+    scope: Vec<Path>,
+    /// The paths of the fields of the tuples being embedded, which are referred to by their
+    /// [Field] in the refinements of the tuple.
+    fields: Vec<(Field, Path)>,
 }
 
 impl EmbeddingCtxt {
     pub fn push_var(&mut self, var: Var) -> usize {
+        self.push_path(Path::from(var))
+    }
+
+    pub fn push_path(&mut self, path: Path) -> usize {
         let index = self.scope.len();
-        self.scope.push(var);
+        self.scope.push(path);
         index
     }
 
-    pub fn pop_var(&mut self) -> Var {
+    pub fn pop_var(&mut self) -> Path {
         self.scope.pop().unwrap()
     }
 
-    fn get_index(&self, target: &Var) -> Option<usize> {
-        for (index, var) in self.scope.iter().enumerate() {
-            if var == target {
-                return Some(index);
+    pub fn push_field(&mut self, fld: Field, path: Path) {
+        self.fields.push((fld, path));
+    }
+
+    pub fn pop_field(&mut self) {
+        self.fields.pop();
+    }
+
+    fn get_index(&self, target: &Path) -> Option<usize> {
+        let target = self.resolve(target);
+        self.scope.iter().rposition(|path| *path == target)
+    }
+
+    /// The indices of the values of `var`: its own index, or the indices of its fields if it is a
+    /// tuple, which is flattened.
+    fn get_indices(&self, var: &Var) -> Vec<usize> {
+        if let Some(index) = self.get_index(&Path::from(var)) {
+            return vec![index];
+        }
+        // A path bound several times is only referred to by its last binding.
+        self.scope
+            .iter()
+            .enumerate()
+            .filter(|(index, path)| path.var == *var && self.get_index(path) == Some(*index))
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// Replace the field `path` starts with, if any, by the path of the field.
+    fn resolve(&self, path: &Path) -> Path {
+        let field_path = match path.var {
+            Var::Field(fld) => {
+                self.fields
+                    .iter()
+                    .rev()
+                    .find_map(|(f, field_path)| if *f == fld { Some(field_path) } else { None })
             }
+            _ => None,
+        };
+
+        match field_path {
+            Some(field_path) => path
+                .projection
+                .iter()
+                .fold(field_path.clone(), |path, n| path.extend(*n)),
+            None => path.clone(),
         }
-        None
     }
 }
 
@@ -31,11 +80,47 @@ impl BaseTy {
     pub fn embed(&self) -> fixpoint::Sort {
         match self {
             Self::Bool => fixpoint::Sort::Bool,
-            Self::Int => fixpoint::Sort::Int,
+            Self::Int(_) | Self::AnyInt => fixpoint::Sort::Int,
+        }
+    }
+
+    /// The assumption every value of this type satisfies, as a predicate on `var`, if any.
+    pub fn embed_invariant(&self, var: &Var, cx: &EmbeddingCtxt) -> Option<fixpoint::Pred> {
+        match self {
+            Self::Int(int_ty) => {
+                let index = cx.get_index(&Path::from(var))?;
+                Some(fixpoint::Pred::Expr(int_ty.embed_range(index)))
+            }
+            Self::AnyInt | Self::Bool => None,
         }
     }
 }
 
+impl IntTy {
+    /// The predicate `min <= v && v <= max` for the variable `v` at `index`, where `min` and `max`
+    /// are the bounds of this kind.
+    fn embed_range(&self, index: usize) -> fixpoint::Expr {
+        use fixpoint::{Constant, Expr, UnOp};
+
+        let var = || Box::new(Expr::Variable(index));
+        let min = if self.is_signed() {
+            Expr::UnaryOp(
+                UnOp::Neg,
+                Box::new(Expr::Constant(Constant::Int(self.min_abs()))),
+            )
+        } else {
+            Expr::Constant(Constant::Int(0))
+        };
+        let max = Expr::Constant(Constant::Int(self.max()));
+
+        Expr::BinaryOp(
+            BinOp::And,
+            Box::new(Expr::BinaryOp(BinOp::Gte, var(), Box::new(min))),
+            Box::new(Expr::BinaryOp(BinOp::Lte, var(), Box::new(max))),
+        )
+    }
+}
+
 impl KVid {
     pub fn embed(&self) -> fixpoint::KVid {
         fixpoint::KVid::from_usize(self.as_usize())
@@ -49,7 +134,7 @@ impl Refine {
                 kvar.id.embed(),
                 kvar.vars
                     .iter()
-                    .filter_map(|var| cx.get_index(var))
+                    .flat_map(|var| cx.get_indices(var))
                     .collect(),
             ),
             Refine::Pred(pred) => fixpoint::Pred::Expr(pred.embed(cx)),
@@ -60,9 +145,9 @@ impl Refine {
 impl PredS {
     pub fn embed(&self, cx: &EmbeddingCtxt) -> fixpoint::Expr {
         match self.kind() {
-            PredKind::Path(path) => {
-                assert_eq!(0, path.projection.len());
-                fixpoint::Expr::Variable(cx.get_index(&path.var).unwrap())
+            PredKind::Path(path) => fixpoint::Expr::Variable(cx.get_index(path).unwrap()),
+            PredKind::BinaryOp(bin_op @ (BinOp::Div | BinOp::Rem), op1, op2) => {
+                embed_truncating(*bin_op, op1.embed(cx), op2.embed(cx))
             }
             PredKind::BinaryOp(bin_op, op1, op2) => {
                 fixpoint::Expr::BinaryOp(*bin_op, Box::new(op1.embed(cx)), Box::new(op2.embed(cx)))
@@ -72,3 +157,31 @@ impl PredS {
         }
     }
 }
+
+/// Rust's division and remainder, which truncate toward zero, in terms of fixpoint's Euclidean
+/// ones. They coincide for a non-negative dividend, and negating the dividend negates the result
+/// of both Rust operators, so `op1 / op2` is `if op1 >= 0 then op1 / op2 else -((-op1) / op2)`.
+fn embed_truncating(bin_op: BinOp, op1: fixpoint::Expr, op2: fixpoint::Expr) -> fixpoint::Expr {
+    use fixpoint::{Constant, Expr, UnOp};
+
+    let euclidean = |op1, op2: &Expr| {
+        let bin_op = if bin_op == BinOp::Div {
+            BinOp::Div
+        } else {
+            BinOp::Mod
+        };
+        Expr::BinaryOp(bin_op, Box::new(op1), Box::new(op2.clone()))
+    };
+    let neg = |op| Expr::UnaryOp(UnOp::Neg, Box::new(op));
+
+    let is_non_negative = Expr::BinaryOp(
+        BinOp::Gte,
+        Box::new(op1.clone()),
+        Box::new(Expr::Constant(Constant::Int(0))),
+    );
+    Expr::IfThenElse(
+        Box::new(is_non_negative),
+        Box::new(euclidean(op1.clone(), &op2)),
+        Box::new(neg(euclidean(neg(op1), &op2))),
+    )
+}
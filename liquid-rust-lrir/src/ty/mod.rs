@@ -11,9 +11,12 @@ pub use crate::mir::{Local, Place};
 use liquid_rust_common::{index::newtype_index, ordered_map::OrderedMap};
 
 use hashconsing::HConsed;
-use std::{fmt, iter::FromIterator};
+use rustc_hir::{def_id::DefId, Mutability};
+use rustc_middle::ty as rs;
+use std::{collections::HashMap, fmt, iter::FromIterator};
 
 /// A function type signature
+#[derive(Clone)]
 pub struct FnSig {
     /// A mapping between ghost variables and their required types. From caller's perspective, ghost
     /// variables in this mapping are universally quantified and need to be instantiated at the
@@ -26,15 +29,142 @@ pub struct FnSig {
     /// variables in this mapping are existentially quantified and can be _assumed_ at the call
     /// site after the function returns.
     pub ensures: Vec<(GhostVar, Ty)>,
-    // FIXME: bring back outputs once we properly handle references
-    // /// Updated ghost variables (bound in [ensures](Self::ensures)) for argument [references](TyKind::Ref)
-    // /// Arguments are referenced by specifying their index in [inputs](Self::inputs).
-    // pub outputs: Vec<GhostVar>,
+    /// Updated ghost variables (bound in [ensures](Self::ensures)) for the pointees of mutable
+    /// argument [references](TyKind::Ref). Arguments are referenced by specifying their index in
+    /// [inputs](Self::inputs).
+    pub outputs: Vec<(usize, GhostVar)>,
     /// Ghost variable (bound in [ensures](Self::ensures)) corresponding to the output of this
     /// function.
     pub output: GhostVar,
 }
 
+impl FnSig {
+    /// The type of the output, bound in [ensures](Self::ensures).
+    pub fn output_ty(&self) -> &Ty {
+        self.ensures
+            .iter()
+            .find_map(|(gv, ty)| if *gv == self.output { Some(ty) } else { None })
+            .unwrap()
+    }
+
+    /// Replace every [`int`](BaseTy::AnyInt) with the kind of the corresponding integer in
+    /// `fn_sig`, the Rust signature of the function. Returns `None` if a base type doesn't match the
+    /// Rust type it refines.
+    pub fn with_int_tys(&self, fn_sig: rs::FnSig, tcx: &TyCtxt) -> Option<FnSig> {
+        if self.inputs.len() != fn_sig.inputs().len() {
+            return None;
+        }
+
+        let mut requires = self.requires.clone();
+        for (gv, rs_ty) in self.inputs.iter().zip(fn_sig.inputs()) {
+            // The pointee of a reference is bound to a ghost variable of its own.
+            let (gv, rs_ty) = match (requires[gv].kind(), rs_ty.kind()) {
+                (TyKind::Ref(bk, _, pointee), rs::TyKind::Ref(_, rs_ty, mutbl))
+                    if *bk == BorrowKind::from(*mutbl) =>
+                {
+                    (*pointee, rs_ty)
+                }
+                _ => (*gv, rs_ty),
+            };
+            let ty = with_int_tys(&requires[&gv], rs_ty, tcx)?;
+            requires.insert(gv, ty);
+        }
+
+        // The updated pointees of mutable references refine the pointees of the inputs.
+        let mut rs_tys = HashMap::new();
+        for (i, gv) in &self.outputs {
+            match fn_sig.inputs()[*i].kind() {
+                rs::TyKind::Ref(_, rs_ty, Mutability::Mut) => rs_tys.insert(*gv, *rs_ty),
+                _ => return None,
+            };
+        }
+        match (self.output_ty().kind(), fn_sig.output().kind()) {
+            (TyKind::Ref(bk, _, pointee), rs::TyKind::Ref(_, rs_ty, mutbl))
+                if *bk == BorrowKind::from(*mutbl) =>
+            {
+                rs_tys.insert(*pointee, *rs_ty)
+            }
+            _ => rs_tys.insert(self.output, fn_sig.output()),
+        };
+        let ensures = self
+            .ensures
+            .iter()
+            .map(|(gv, ty)| {
+                let ty = match rs_tys.get(gv) {
+                    Some(rs_ty) => with_int_tys(ty, *rs_ty, tcx)?,
+                    None => ty.clone(),
+                };
+                Some((*gv, ty))
+            })
+            .collect::<Option<_>>()?;
+
+        Some(FnSig {
+            requires,
+            inputs: self.inputs.clone(),
+            ensures,
+            outputs: self.outputs.clone(),
+            output: self.output,
+        })
+    }
+}
+
+/// See [FnSig::with_int_tys].
+pub fn with_int_tys(ty: &Ty, rs_ty: rs::Ty, tcx: &TyCtxt) -> Option<Ty> {
+    match (ty.kind(), rs_ty.kind()) {
+        (TyKind::Refined(bty, refine), _) => {
+            let bty = match (bty, rs_ty.kind()) {
+                (BaseTy::Bool, rs::TyKind::Bool) => BaseTy::Bool,
+                (BaseTy::AnyInt, _) => BaseTy::Int(IntTy::of(rs_ty)?),
+                (BaseTy::Int(int_ty), _) if Some(*int_ty) == IntTy::of(rs_ty) => *bty,
+                _ => return None,
+            };
+            Some(tcx.mk_refine(bty, refine.clone()))
+        }
+        (TyKind::Tuple(tup), rs::TyKind::Tuple(_)) if tup.len() == rs_ty.tuple_fields().count() => {
+            let tup = tup
+                .iter()
+                .zip(rs_ty.tuple_fields())
+                .map(|((fld, ty), rs_ty)| Some((*fld, with_int_tys(ty, rs_ty, tcx)?)))
+                .collect::<Option<_>>()?;
+            Some(tcx.mk_tuple(tup))
+        }
+        // The fields of a struct are refined when the struct is defined.
+        (TyKind::Adt(adt_def, _), rs::TyKind::Adt(rs_adt_def, _))
+            if adt_def.def_id == rs_adt_def.did =>
+        {
+            Some(ty.clone())
+        }
+        // The fields of a variant are either of a base type of the enum or of one of its type
+        // arguments.
+        (TyKind::Enum(enum_def, variant, variants), rs::TyKind::Adt(rs_adt_def, substs))
+            if enum_def.def_id == rs_adt_def.did =>
+        {
+            let variants = variants
+                .iter()
+                .zip(&enum_def.variants)
+                .map(|(ty, variant_def)| match ty.kind() {
+                    TyKind::Tuple(tup) => {
+                        let tup = tup
+                            .iter()
+                            .zip(&variant_def.fields)
+                            .map(|((fld, ty), field)| match field {
+                                VariantField::Param(i) => {
+                                    Some((*fld, with_int_tys(ty, substs.type_at(*i), tcx)?))
+                                }
+                                VariantField::Base(_) => Some((*fld, ty.clone())),
+                            })
+                            .collect::<Option<_>>()?;
+                        Some(tcx.mk_tuple(tup))
+                    }
+                    _ => None,
+                })
+                .collect::<Option<_>>()?;
+            Some(tcx.mk_enum(enum_def.clone(), *variant, variants))
+        }
+        _ => None,
+    }
+}
+
 pub type Ty = HConsed<TyS>;
 
 #[derive(Clone, PartialEq, Eq, Hash)]
@@ -49,7 +179,7 @@ impl TyS {
 
     /// Whether this type is a refined integer type.
     pub fn is_int(&self) -> bool {
-        matches!(self.kind(), TyKind::Refined(BaseTy::Int, ..))
+        matches!(self.kind(), TyKind::Refined(bty, ..) if bty.is_int())
     }
 
     /// Whether this type is a refined boolean type.
@@ -57,11 +187,22 @@ impl TyS {
         matches!(self.kind(), TyKind::Refined(BaseTy::Bool, ..))
     }
 
+    /// The base type of a refined type.
+    pub fn base_ty(&self) -> Option<BaseTy> {
+        match self.kind() {
+            TyKind::Refined(bty, _) => Some(*bty),
+            _ => None,
+        }
+    }
+
     /// Whether the type is copy.
     pub fn is_copy(&self) -> bool {
         match self.kind() {
             TyKind::Ref(BorrowKind::Shared, ..) | TyKind::Refined(_, _) => true,
-            TyKind::Tuple(tup) => tup.types().all(|ty| ty.is_copy()),
+            TyKind::Tuple(tup) | TyKind::Adt(_, tup) => tup.types().all(|ty| ty.is_copy()),
+            // Only the fields of the variant the enum is known to be, if any, are there.
+            TyKind::Enum(_, Some(variant), variants) => variants[*variant].is_copy(),
+            TyKind::Enum(_, None, variants) => variants.iter().all(|ty| ty.is_copy()),
             _ => false,
         }
     }
@@ -70,7 +211,11 @@ impl TyS {
     pub fn size(&self) -> usize {
         match self.kind() {
             TyKind::Refined(bty, _) => bty.size(),
-            TyKind::Tuple(tup) => tup.types().map(|ty| ty.size()).sum(),
+            TyKind::Tuple(tup) | TyKind::Adt(_, tup) => tup.types().map(|ty| ty.size()).sum(),
+            // The discriminant and the largest variant.
+            TyKind::Enum(_, _, variants) => {
+                1 + variants.iter().map(|ty| ty.size()).max().unwrap_or(0)
+            }
             TyKind::Ref(_, _, _) => 1,
             TyKind::Uninit(size) => *size,
         }
@@ -90,6 +235,30 @@ impl fmt::Display for TyS {
                     .join(", ");
                 write!(f, "({})", tup)
             }
+            TyKind::Adt(adt_def, tup) => {
+                let fields = adt_def
+                    .field_names
+                    .iter()
+                    .zip(tup.types())
+                    .map(|(name, ty)| format!("{}: {}", name, ty))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                write!(f, "{} {{ {} }}", adt_def.name, fields)
+            }
+            TyKind::Enum(enum_def, Some(variant), variants) => {
+                let name = &enum_def.variants[*variant].name;
+                write!(f, "{}::{}{}", enum_def.name, name, variants[*variant])
+            }
+            TyKind::Enum(enum_def, None, variants) => {
+                let variants = enum_def
+                    .variants
+                    .iter()
+                    .zip(variants)
+                    .map(|(variant_def, ty)| format!("{}{}", variant_def.name, ty))
+                    .collect::<Vec<_>>()
+                    .join(" | ");
+                write!(f, "{}::{{{}}}", enum_def.name, variants)
+            }
             TyKind::Uninit(size) => write!(f, "uninit({})", size),
             TyKind::Refined(bty, Refine::Infer(k)) => write!(f, "{{ {} | {} }}", bty, k),
             TyKind::Refined(bty, Refine::Pred(pred)) => {
@@ -110,12 +279,65 @@ pub enum TyKind {
     Refined(BaseTy, Refine),
     /// A dependent tuple: `(x: int, y: {int | x > v})`.
     Tuple(Tuple),
+    /// A struct, with the types of its fields in declaration order. Like in a tuple, the type of a
+    /// field may depend on the fields before it.
+    Adt(AdtDef, Tuple),
+    /// An enum, with a tuple type for the fields of each of its variants and the variant it is
+    /// known to be, if any. The fields of a variant are only bound while the enum is known to be
+    /// that variant, e.g., in the branch of a `match` for it.
+    Enum(EnumDef, Option<usize>, Vec<Ty>),
     /// A borrowed reference.
     Ref(BorrowKind, Region, GhostVar),
     /// Uninitialized memory of given size.
     Uninit(usize),
 }
 
+pub type AdtDef = HConsed<AdtDefS>;
+
+/// The definition of a struct whose fields may be refined.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct AdtDefS {
+    pub def_id: DefId,
+    pub name: String,
+    /// The module defining the struct.
+    pub module: DefId,
+    pub field_names: Vec<String>,
+    /// The types of the fields, which every value of the struct must have, i.e., the invariant of
+    /// the struct. The type of a field may refer to the fields before it.
+    pub fields: Tuple,
+}
+
+pub type EnumDef = HConsed<EnumDefS>;
+
+/// The definition of an enum whose variants may have refined fields. The discriminant of each
+/// variant is its index.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct EnumDefS {
+    pub def_id: DefId,
+    pub name: String,
+    /// The module defining the enum.
+    pub module: DefId,
+    /// The number of type parameters of the enum.
+    pub params: usize,
+    pub variants: Vec<VariantDef>,
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct VariantDef {
+    pub name: String,
+    pub fields: Vec<VariantField>,
+}
+
+/// The type of a field of an enum variant.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VariantField {
+    /// A type parameter of the enum, by index, e.g., the `T` in `Some(T)`. The field has the type
+    /// the parameter is instantiated with, which may be refined.
+    Param(usize),
+    /// A base type, which is never refined.
+    Base(BaseTy),
+}
+
 #[derive(Clone, PartialEq, Eq, Hash)]
 pub struct Tuple(Vec<(Field, Ty)>);
 
@@ -356,7 +578,11 @@ impl fmt::Display for Var {
 /// A base type than can refined.
 #[derive(Clone, Copy, PartialEq, Eq, Hash)]
 pub enum BaseTy {
-    Int,
+    /// A machine integer. Values of this type are always within the range of its kind.
+    Int(IntTy),
+    /// An integer of the same kind as the Rust value it refines, i.e., `int` in annotations. It is
+    /// replaced by that kind once the Rust type is known, see [FnSig::with_int_tys].
+    AnyInt,
     Bool,
 }
 
@@ -364,19 +590,140 @@ impl BaseTy {
     pub fn size(&self) -> usize {
         1
     }
+
+    /// Whether this is an integer type.
+    pub fn is_int(&self) -> bool {
+        matches!(self, BaseTy::Int(_) | BaseTy::AnyInt)
+    }
 }
 
 impl fmt::Display for BaseTy {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            BaseTy::Int => write!(f, "int"),
+            BaseTy::Int(int_ty) => write!(f, "{}", int_ty),
+            BaseTy::AnyInt => write!(f, "int"),
             BaseTy::Bool => write!(f, "bool"),
         }
     }
 }
 
+/// The kind of a machine integer, i.e., its width and signedness.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum IntTy {
+    I8,
+    I16,
+    I32,
+    I64,
+    I128,
+    Isize,
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+    Usize,
+}
+
+impl IntTy {
+    pub fn is_signed(&self) -> bool {
+        matches!(
+            self,
+            IntTy::I8 | IntTy::I16 | IntTy::I32 | IntTy::I64 | IntTy::I128 | IntTy::Isize
+        )
+    }
+
+    /// Width in bits.
+    pub fn bit_width(&self) -> u32 {
+        match self {
+            IntTy::I8 | IntTy::U8 => 8,
+            IntTy::I16 | IntTy::U16 => 16,
+            IntTy::I32 | IntTy::U32 => 32,
+            IntTy::I64 | IntTy::U64 => 64,
+            IntTy::I128 | IntTy::U128 => 128,
+            // FIXME: this assumes a 64-bit target.
+            IntTy::Isize | IntTy::Usize => 64,
+        }
+    }
+
+    /// The absolute value of the smallest value of this kind, which is negative for signed kinds.
+    pub fn min_abs(&self) -> u128 {
+        if self.is_signed() {
+            1 << (self.bit_width() - 1)
+        } else {
+            0
+        }
+    }
+
+    /// The largest value of this kind.
+    pub fn max(&self) -> u128 {
+        if self.is_signed() {
+            (1 << (self.bit_width() - 1)) - 1
+        } else {
+            u128::MAX >> (128 - self.bit_width())
+        }
+    }
+
+    /// The kind of the Rust integer type `ty`, if it is one.
+    pub fn of(ty: rs::Ty) -> Option<IntTy> {
+        match ty.kind() {
+            rs::TyKind::Int(int_ty) => Some((*int_ty).into()),
+            rs::TyKind::Uint(uint_ty) => Some((*uint_ty).into()),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            IntTy::I8 => "i8",
+            IntTy::I16 => "i16",
+            IntTy::I32 => "i32",
+            IntTy::I64 => "i64",
+            IntTy::I128 => "i128",
+            IntTy::Isize => "isize",
+            IntTy::U8 => "u8",
+            IntTy::U16 => "u16",
+            IntTy::U32 => "u32",
+            IntTy::U64 => "u64",
+            IntTy::U128 => "u128",
+            IntTy::Usize => "usize",
+        }
+    }
+}
+
+impl From<rs::IntTy> for IntTy {
+    fn from(int_ty: rs::IntTy) -> Self {
+        match int_ty {
+            rs::IntTy::I8 => IntTy::I8,
+            rs::IntTy::I16 => IntTy::I16,
+            rs::IntTy::I32 => IntTy::I32,
+            rs::IntTy::I64 => IntTy::I64,
+            rs::IntTy::I128 => IntTy::I128,
+            rs::IntTy::Isize => IntTy::Isize,
+        }
+    }
+}
+
+impl From<rs::UintTy> for IntTy {
+    fn from(uint_ty: rs::UintTy) -> Self {
+        match uint_ty {
+            rs::UintTy::U8 => IntTy::U8,
+            rs::UintTy::U16 => IntTy::U16,
+            rs::UintTy::U32 => IntTy::U32,
+            rs::UintTy::U64 => IntTy::U64,
+            rs::UintTy::U128 => IntTy::U128,
+            rs::UintTy::Usize => IntTy::Usize,
+        }
+    }
+}
+
+impl fmt::Display for IntTy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
 /// A region corresponds to an approximate set of possible provenances for a reference.
-#[derive(Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Region {
     /// A concrete (approximate) provenance set.
     Concrete(Vec<Place>),
@@ -386,6 +733,32 @@ pub enum Region {
     Infer(RegionVid),
 }
 
+impl Region {
+    /// The smallest region containing both `self` and `other`.
+    pub fn join(&self, other: &Region) -> Region {
+        match (self, other) {
+            _ if self == other => self.clone(),
+            (Region::Concrete(places), region) | (region, Region::Concrete(places))
+                if places.is_empty() =>
+            {
+                region.clone()
+            }
+            (Region::Concrete(places1), Region::Concrete(places2)) => {
+                let mut places = places1.clone();
+                for place in places2 {
+                    if !places.contains(place) {
+                        places.push(place.clone());
+                    }
+                }
+                Region::Concrete(places)
+            }
+            // References to the memory of a caller only live in the arguments, which are never
+            // assigned. Elsewhere they are reborrowed through the argument.
+            _ => unreachable!("joining regions {} and {}", self, other),
+        }
+    }
+}
+
 impl From<Place> for Region {
     fn from(place: Place) -> Self {
         Region::Concrete(vec![place])
@@ -415,6 +788,15 @@ pub enum BorrowKind {
     Mut,
 }
 
+impl From<Mutability> for BorrowKind {
+    fn from(mutbl: Mutability) -> Self {
+        match mutbl {
+            Mutability::Not => BorrowKind::Shared,
+            Mutability::Mut => BorrowKind::Mut,
+        }
+    }
+}
+
 /// A k-variable correspond to a refinement predicate that needs to be inferred. They are called
 /// k-variables because the greek letter kappa is typically used to range over these variables when
 /// formalized.
@@ -1,74 +1,177 @@
+use std::collections::HashMap;
+
 use liquid_rust_common::index::IndexGen;
+use rustc_hir::{def_id::DefId, Mutability};
 use rustc_index::bit_set::BitSet;
 use rustc_middle::{mir, ty as rs};
 use rustc_mir::dataflow::move_paths::{LookupResult, MoveData, MovePathIndex};
+use rustc_span::Symbol;
+use rustc_target::abi::VariantIdx;
 
-use super::{BaseTy, Field, KVid, Kvar, Local, Ty, TyCtxt, Var};
+use super::{
+    AdtDef, BaseTy, BorrowKind, EnumDef, Field, FnSig, GhostVar, KVid, Kvar, Local, Region, Tuple,
+    Ty, TyCtxt, UniversalRegion, Var,
+};
 
 pub struct Refiner<'a, 'tcx> {
     tcx: &'a TyCtxt,
+    rs_tcx: rs::TyCtxt<'tcx>,
     move_data: &'a MoveData<'tcx>,
     maybe_uninit: &'a BitSet<MovePathIndex>,
     kvid_gen: &'a IndexGen<KVid>,
+    ghost_gen: &'a IndexGen<GhostVar>,
+    /// The region of every local holding a reference.
+    regions: &'a HashMap<Local, Region>,
 }
 
 impl<'a, 'tcx> Refiner<'a, 'tcx> {
     pub fn new(
         tcx: &'a TyCtxt,
+        rs_tcx: rs::TyCtxt<'tcx>,
         move_data: &'a MoveData<'tcx>,
         maybe_uninit: &'a BitSet<MovePathIndex>,
         kvid_gen: &'a IndexGen<KVid>,
+        ghost_gen: &'a IndexGen<GhostVar>,
+        regions: &'a HashMap<Local, Region>,
     ) -> Self {
         Self {
             tcx,
+            rs_tcx,
             move_data,
             maybe_uninit,
             kvid_gen,
+            ghost_gen,
+            regions,
         }
     }
 
+    /// Returns a type with the shape of `ty` for `local`, with a fresh k-variable for each
+    /// refinement. The pointee of a reference is bound to a fresh ghost variable, which is pushed
+    /// to `pointees` along with its type and added to `vars_in_scope`.
     pub fn maybe_uninit(
         &mut self,
         ty: rs::Ty<'tcx>,
         local: Local,
         vars_in_scope: &mut Vec<Var>,
+        pointees: &mut Vec<(GhostVar, Ty)>,
     ) -> Ty {
         let mut cx = RefinerCtxt {
             local,
             vars_in_scope,
+            pointees,
             projection: vec![],
         };
         self.maybe_uninit_with_cx(ty, &mut cx)
     }
 
     fn maybe_uninit_with_cx(&mut self, ty: rs::Ty<'tcx>, cx: &mut RefinerCtxt<'_, 'tcx>) -> Ty {
-        if self.is_maybe_uninit(cx) {
+        // The fields of a tuple or struct may be initialized one by one, so each one is checked on
+        // its own.
+        let is_aggregate = matches!(ty.kind(), rs::TyKind::Tuple(_) | rs::TyKind::Adt(..));
+        if !is_aggregate && self.is_maybe_uninit(cx) {
             return Refiner::uninit(self.tcx, ty);
         }
 
         let tcx = self.tcx;
         match ty.kind() {
             rs::TyKind::Tuple(_) => {
-                let tup = ty
-                    .tuple_fields()
-                    .enumerate()
-                    .map(|(i, ty)| {
-                        let fld = Field::from_usize(i);
-                        cx.push_field(i, ty);
-                        let ty = self.maybe_uninit_with_cx(ty, cx);
-                        cx.pop_field();
-                        (fld, ty)
+                let tup = self.maybe_uninit_fields(ty.tuple_fields().collect(), cx);
+                tcx.mk_tuple(tup)
+            }
+            // The enum may be any of its variants, so the fields of each one are refined.
+            rs::TyKind::Adt(rs_adt_def, substs) if rs_adt_def.is_enum() => {
+                let enum_def = enum_def(tcx, rs_adt_def.did);
+                let variants = rs_adt_def
+                    .variants
+                    .iter_enumerated()
+                    .map(|(variant, variant_def)| {
+                        let field_tys = variant_def
+                            .fields
+                            .iter()
+                            .map(|field| field.ty(self.rs_tcx, substs))
+                            .collect();
+                        cx.push_downcast(variant_def.ident.name, variant);
+                        let tup = self.maybe_uninit_fields(field_tys, cx);
+                        cx.pop_projection();
+                        tcx.mk_tuple(tup)
                     })
                     .collect();
-                self.tcx.mk_tuple(tup)
+                tcx.mk_enum(enum_def, None, variants)
+            }
+            // A struct satisfies its invariant once all its fields are initialized, before that it
+            // is refined like a tuple.
+            rs::TyKind::Adt(rs_adt_def, substs) => {
+                let adt_def = adt_def(tcx, rs_adt_def.did);
+                let field_tys = rs_adt_def
+                    .non_enum_variant()
+                    .fields
+                    .iter()
+                    .map(|field| field.ty(self.rs_tcx, substs))
+                    .collect::<Vec<_>>();
+                let initialized = field_tys.iter().enumerate().all(|(i, &ty)| {
+                    cx.push_field(i, ty);
+                    let initialized = !self.is_maybe_uninit(cx);
+                    cx.pop_projection();
+                    initialized
+                });
+                if initialized {
+                    tcx.mk_adt_invariant(&adt_def)
+                } else {
+                    let tup = self.maybe_uninit_fields(field_tys, cx);
+                    tcx.mk_adt(adt_def, tup)
+                }
             }
             rs::TyKind::Bool => tcx.mk_refine(BaseTy::Bool, self.fresh_kvar(cx)),
-            rs::TyKind::Int(_) | rs::TyKind::Uint(_) => {
-                tcx.mk_refine(BaseTy::Int, self.fresh_kvar(cx))
+            rs::TyKind::Int(int_ty) => {
+                tcx.mk_refine(BaseTy::Int((*int_ty).into()), self.fresh_kvar(cx))
             }
-            rs::TyKind::Ref(..) => todo!(),
-            _ => todo!(),
+            rs::TyKind::Uint(uint_ty) => {
+                tcx.mk_refine(BaseTy::Int((*uint_ty).into()), self.fresh_kvar(cx))
+            }
+            // No value of type `!` can ever be assigned.
+            rs::TyKind::Never => tcx.mk_uninit(0),
+            // References are only supported in locals, so the region is the one of the local. A
+            // reference that is never assigned can only be in dead code, where any region will do.
+            rs::TyKind::Ref(_, ty, mutbl) => {
+                let pointee = self.maybe_uninit_with_cx(ty, cx);
+                let fresh_gv = self.ghost_gen.fresh();
+                cx.pointees.push((fresh_gv, pointee));
+                cx.vars_in_scope.push(fresh_gv.into());
+                let region = self
+                    .regions
+                    .get(&cx.local)
+                    .cloned()
+                    .unwrap_or_else(|| Region::Concrete(vec![]));
+                tcx.mk_ref(BorrowKind::from(*mutbl), region, fresh_gv)
+            }
+            _ => unsupported(ty),
+        }
+    }
+
+    /// Refine the fields of a tuple or struct with types `tys`. The refinement of each field may
+    /// depend on the fields before it.
+    fn maybe_uninit_fields(
+        &mut self,
+        tys: Vec<rs::Ty<'tcx>>,
+        cx: &mut RefinerCtxt<'_, 'tcx>,
+    ) -> Tuple {
+        let tup = tys
+            .iter()
+            .copied()
+            .enumerate()
+            .map(|(i, ty)| {
+                let fld = Field::from_usize(i);
+                cx.push_field(i, ty);
+                let ty = self.maybe_uninit_with_cx(ty, cx);
+                cx.pop_projection();
+                cx.vars_in_scope.push(fld.into());
+                (fld, ty)
+            })
+            .collect();
+        for _ in &tys {
+            cx.vars_in_scope.pop();
         }
+        tup
     }
 
     pub fn uninit(tcx: &TyCtxt, ty: rs::Ty) -> Ty {
@@ -81,11 +184,111 @@ impl<'a, 'tcx> Refiner<'a, 'tcx> {
                     .collect();
                 tcx.mk_tuple(tup)
             }
+            rs::TyKind::Adt(rs_adt_def, substs) if rs_adt_def.is_enum() => {
+                let args = substs
+                    .types()
+                    .map(|ty| Refiner::uninit(tcx, ty))
+                    .collect::<Vec<_>>();
+                let enum_def = enum_def(tcx, rs_adt_def.did);
+                let variants = tcx.mk_enum_variants(&enum_def, &args);
+                tcx.uninitialize(&tcx.mk_enum(enum_def, None, variants))
+            }
+            rs::TyKind::Adt(rs_adt_def, _) => {
+                tcx.uninitialize(&tcx.mk_adt_invariant(&adt_def(tcx, rs_adt_def.did)))
+            }
             // FIXME: use actual sizes
             rs::TyKind::Ref(..) | rs::TyKind::Bool | rs::TyKind::Int(_) | rs::TyKind::Uint(_) => {
                 tcx.mk_uninit(1)
             }
-            _ => todo!(),
+            rs::TyKind::Never => tcx.mk_uninit(0),
+            _ => unsupported(ty),
+        }
+    }
+
+    /// Returns a type with the shape of `ty` where every refinement is trivially true.
+    pub fn trivial(tcx: &TyCtxt, ty: rs::Ty) -> Ty {
+        match ty.kind() {
+            rs::TyKind::Tuple(_) => {
+                let tup = ty
+                    .tuple_fields()
+                    .enumerate()
+                    .map(|(i, ty)| (Field::from_usize(i), Refiner::trivial(tcx, ty)))
+                    .collect();
+                tcx.mk_tuple(tup)
+            }
+            rs::TyKind::Adt(rs_adt_def, substs) if rs_adt_def.is_enum() => {
+                let args = substs
+                    .types()
+                    .map(|ty| Refiner::trivial(tcx, ty))
+                    .collect::<Vec<_>>();
+                let enum_def = enum_def(tcx, rs_adt_def.did);
+                let variants = tcx.mk_enum_variants(&enum_def, &args);
+                tcx.mk_enum(enum_def, None, variants)
+            }
+            // Every value of a struct satisfies its invariant.
+            rs::TyKind::Adt(rs_adt_def, _) => tcx.mk_adt_invariant(&adt_def(tcx, rs_adt_def.did)),
+            rs::TyKind::Bool => tcx.mk_refine(BaseTy::Bool, tcx.preds.tt()),
+            rs::TyKind::Int(int_ty) => tcx.mk_refine(BaseTy::Int((*int_ty).into()), tcx.preds.tt()),
+            rs::TyKind::Uint(uint_ty) => {
+                tcx.mk_refine(BaseTy::Int((*uint_ty).into()), tcx.preds.tt())
+            }
+            // Diverging functions never return a value.
+            rs::TyKind::Never => tcx.mk_uninit(0),
+            _ => unsupported(ty),
+        }
+    }
+
+    /// Returns the signature assumed for a function without a refined signature, i.e., a
+    /// signature with the shape of `fn_sig` where every refinement is trivially true. Calling
+    /// such a function requires nothing and ensures nothing about its output or the pointees of
+    /// its mutable references.
+    pub fn default_fn_sig(tcx: &TyCtxt, fn_sig: rs::FnSig<'tcx>) -> FnSig {
+        let ghost_gen = IndexGen::<GhostVar>::new();
+        let region_gen = IndexGen::<UniversalRegion>::new();
+        // Each lifetime in `fn_sig` becomes a universal region, so an output borrowing from an
+        // argument has the region of that argument.
+        let mut regions = HashMap::new();
+        let mut trivial = |ty: rs::Ty<'tcx>, pointees: &mut Vec<(GhostVar, Ty)>| match ty.kind() {
+            // The pointee of a reference is bound to a ghost variable of its own.
+            rs::TyKind::Ref(region, ty, mutbl) => {
+                let pointee = ghost_gen.fresh();
+                pointees.push((pointee, Refiner::trivial(tcx, ty)));
+                let region = *regions.entry(*region).or_insert_with(|| region_gen.fresh());
+                tcx.mk_ref(BorrowKind::from(*mutbl), Region::Abstract(region), pointee)
+            }
+            _ => Refiner::trivial(tcx, ty),
+        };
+
+        let mut requires = vec![];
+        let mut inputs = vec![];
+        for ty in fn_sig.inputs() {
+            let ty = trivial(ty, &mut requires);
+            let fresh_gv = ghost_gen.fresh();
+            requires.push((fresh_gv, ty));
+            inputs.push(fresh_gv);
+        }
+
+        // The pointees of mutable references may be updated to any value of their type.
+        let mut ensures = vec![];
+        let mut outputs = vec![];
+        for (i, ty) in fn_sig.inputs().iter().enumerate() {
+            if let rs::TyKind::Ref(_, ty, Mutability::Mut) = ty.kind() {
+                let fresh_gv = ghost_gen.fresh();
+                ensures.push((fresh_gv, Refiner::trivial(tcx, ty)));
+                outputs.push((i, fresh_gv));
+            }
+        }
+
+        let ty = trivial(fn_sig.output(), &mut ensures);
+        let output = ghost_gen.fresh();
+        ensures.push((output, ty));
+
+        FnSig {
+            requires: requires.into_iter().collect(),
+            inputs,
+            ensures,
+            outputs,
+            output,
         }
     }
 
@@ -115,21 +318,42 @@ impl<'a, 'tcx> Refiner<'a, 'tcx> {
     }
 }
 
+// Values of a type that cannot be refined, e.g., a struct with an invalid annotation, are rejected
+// when the MIR is lowered, so the functions below are never called on them.
+
+fn adt_def(tcx: &TyCtxt, def_id: DefId) -> AdtDef {
+    tcx.adt_def(def_id)
+        .unwrap_or_else(|| unreachable!("struct `{:?}` cannot be refined", def_id))
+}
+
+fn enum_def(tcx: &TyCtxt, def_id: DefId) -> EnumDef {
+    tcx.enum_def(def_id)
+        .unwrap_or_else(|| unreachable!("enum `{:?}` cannot be refined", def_id))
+}
+
+fn unsupported(ty: rs::Ty) -> ! {
+    unreachable!("values of type `{}` cannot be refined", ty)
+}
+
 struct RefinerCtxt<'a, 'tcx> {
     local: Local,
     vars_in_scope: &'a mut Vec<Var>,
+    pointees: &'a mut Vec<(GhostVar, Ty)>,
     projection: Vec<mir::PlaceElem<'tcx>>,
 }
 
 impl<'a, 'tcx> RefinerCtxt<'a, 'tcx> {
     fn push_field(&mut self, i: usize, ty: rs::Ty<'tcx>) {
-        self.vars_in_scope.push(Field::from_usize(i).into());
         self.projection
             .push(mir::PlaceElem::Field(mir::Field::from_usize(i), ty));
     }
 
-    fn pop_field(&mut self) {
-        self.vars_in_scope.pop();
+    fn push_downcast(&mut self, name: Symbol, variant: VariantIdx) {
+        self.projection
+            .push(mir::PlaceElem::Downcast(Some(name), variant));
+    }
+
+    fn pop_projection(&mut self) {
         self.projection.pop();
     }
 }
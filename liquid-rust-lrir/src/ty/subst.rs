@@ -6,6 +6,7 @@ use super::*;
 pub struct Subst {
     ghost_vars: HashMap<GhostVar, GhostVar>,
     fields: HashMap<Field, Field>,
+    regions: HashMap<UniversalRegion, Region>,
 }
 
 impl Subst {
@@ -13,6 +14,7 @@ impl Subst {
         Subst {
             ghost_vars: HashMap::new(),
             fields: HashMap::new(),
+            regions: HashMap::new(),
         }
     }
 
@@ -28,12 +30,20 @@ impl Subst {
                 let ty2 = env2.lookup(gv2);
                 self.infer(env1, ty1, env2, ty2);
             }
-            (TyKind::Tuple(tup1), TyKind::Tuple(tup2)) if tup1.len() == tup2.len() => {
+            (TyKind::Tuple(tup1), TyKind::Tuple(tup2))
+            | (TyKind::Adt(_, tup1), TyKind::Adt(_, tup2))
+                if tup1.len() == tup2.len() =>
+            {
                 for ((fld1, ty1), (fld2, ty2)) in tup1.iter().zip(tup2.iter()) {
                     self.add_field_subst(*fld2, *fld1);
                     self.infer(env1, ty1, env2, ty2);
                 }
             }
+            (TyKind::Enum(_, _, variants1), TyKind::Enum(_, _, variants2)) => {
+                for (ty1, ty2) in variants1.iter().zip(variants2) {
+                    self.infer(env1, ty1, env2, ty2);
+                }
+            }
             _ => {}
         }
     }
@@ -50,10 +60,24 @@ impl Subst {
         self.ghost_vars.insert(l1, l2);
     }
 
+    /// Instantiate `ur` with `region`. A universal region instantiated more than once, e.g., the
+    /// `'a` in `fn<'a>(x: &'a int, y: &'a int)`, is instantiated with the join of the regions.
+    pub fn add_region_subst(&mut self, ur: UniversalRegion, region: &Region) {
+        let region = match self.regions.get(&ur) {
+            Some(old_region) => old_region.join(region),
+            None => region.clone(),
+        };
+        self.regions.insert(ur, region);
+    }
+
     fn get_field(&self, fld: Field) -> Option<Field> {
         self.fields.get(&fld).copied()
     }
 
+    fn get_region(&self, ur: UniversalRegion) -> Option<Region> {
+        self.regions.get(&ur).cloned()
+    }
+
     fn get_ghost_var(&self, l: GhostVar) -> Option<GhostVar> {
         self.ghost_vars.get(&l).copied()
     }
@@ -66,17 +90,34 @@ pub trait ApplySubst {
 impl ApplySubst for Ty {
     fn apply_subst(&self, tcx: &TyCtxt, subst: &Subst) -> Self {
         match self.kind() {
-            TyKind::Ref(bk, r, gv) => tcx.mk_ref(*bk, r.clone(), subst.apply(gv, tcx)),
+            TyKind::Ref(bk, r, gv) => tcx.mk_ref(*bk, subst.apply(r, tcx), subst.apply(gv, tcx)),
             TyKind::Tuple(tup) => {
                 let tup = tup.map(|_, fld, ty| (subst.apply(fld, tcx), subst.apply(ty, tcx)));
                 tcx.mk_tuple(tup)
             }
+            TyKind::Adt(adt_def, tup) => {
+                let tup = tup.map(|_, fld, ty| (subst.apply(fld, tcx), subst.apply(ty, tcx)));
+                tcx.mk_adt(adt_def.clone(), tup)
+            }
+            TyKind::Enum(enum_def, variant, variants) => {
+                let variants = variants.iter().map(|ty| subst.apply(ty, tcx)).collect();
+                tcx.mk_enum(enum_def.clone(), *variant, variants)
+            }
             TyKind::Uninit(_) => self.clone(),
             TyKind::Refined(bty, refine) => tcx.mk_refine(*bty, subst.apply(refine, tcx)),
         }
     }
 }
 
+impl ApplySubst for Region {
+    fn apply_subst(&self, _tcx: &TyCtxt, subst: &Subst) -> Self {
+        match self {
+            Region::Abstract(ur) => subst.get_region(*ur).unwrap_or_else(|| self.clone()),
+            _ => self.clone(),
+        }
+    }
+}
+
 impl ApplySubst for Refine {
     fn apply_subst(&self, tcx: &TyCtxt, subst: &Subst) -> Self {
         match self {
@@ -7,7 +7,7 @@ mod ty;
 pub use ident::Ident;
 pub use op::{BinOp, BinOpKind, UnOp, UnOpKind};
 pub use predicate::{Literal, Predicate, PredicateKind};
-pub use ty::{BaseTy, FnDecl, RefinedTy, Ty, TyKind};
+pub use ty::{BaseTy, FnDecl, IntTy, RefKind, RefinedTy, Ty, TyKind};
 
 /// The span of each AST item, relative to the beginning of the outermost type in the AST.
 pub type Span = std::ops::Range<usize>;
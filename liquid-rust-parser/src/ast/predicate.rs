@@ -17,6 +17,10 @@ pub enum PredicateKind<'source> {
     Lit(Literal),
     /// A path.
     Path(Ident<'source>, Vec<usize>),
+    /// The pointee of a reference: `*x`.
+    Deref(Ident<'source>),
+    /// The value of a predicate before the function was called: `old(*x)`.
+    Old(Box<Predicate<'source>>),
     /// An unary operation between predicates.
     UnaryOp(UnOp, Box<Predicate<'source>>),
     /// A binary operation between predicates.
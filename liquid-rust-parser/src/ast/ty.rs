@@ -1,10 +1,74 @@
 use crate::ast::{ident::Ident, predicate::Predicate, Span};
 
+use std::str::FromStr;
+
 // The AST representation of basic types.
 #[derive(Debug, Copy, Clone)]
 pub enum BaseTy {
     Bool,
+    /// An integer of the same kind as the Rust type being refined.
     Int,
+    /// An integer of a specific kind, e.g., `u8`.
+    IntTy(IntTy),
+}
+
+/// The AST representation of the kinds of machine integers.
+#[derive(Debug, Copy, Clone)]
+pub enum IntTy {
+    I8,
+    I16,
+    I32,
+    I64,
+    I128,
+    Isize,
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+    Usize,
+}
+
+impl IntTy {
+    pub fn name(&self) -> &'static str {
+        match self {
+            IntTy::I8 => "i8",
+            IntTy::I16 => "i16",
+            IntTy::I32 => "i32",
+            IntTy::I64 => "i64",
+            IntTy::I128 => "i128",
+            IntTy::Isize => "isize",
+            IntTy::U8 => "u8",
+            IntTy::U16 => "u16",
+            IntTy::U32 => "u32",
+            IntTy::U64 => "u64",
+            IntTy::U128 => "u128",
+            IntTy::Usize => "usize",
+        }
+    }
+}
+
+impl FromStr for IntTy {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let int_ty = match s {
+            "i8" => IntTy::I8,
+            "i16" => IntTy::I16,
+            "i32" => IntTy::I32,
+            "i64" => IntTy::I64,
+            "i128" => IntTy::I128,
+            "isize" => IntTy::Isize,
+            "u8" => IntTy::U8,
+            "u16" => IntTy::U16,
+            "u32" => IntTy::U32,
+            "u64" => IntTy::U64,
+            "u128" => IntTy::U128,
+            "usize" => IntTy::Usize,
+            _ => return Err(()),
+        };
+        Ok(int_ty)
+    }
 }
 
 /// The AST representation of a refinement type.
@@ -26,12 +90,29 @@ pub enum TyKind<'source> {
     Base(BaseTy),
     Refined(RefinedTy<'source>),
     Tuple(Vec<(Option<Ident<'source>>, Ty<'source>)>),
+    /// A struct or an enum, referred to by its name and type arguments, e.g., `Range` or
+    /// `Option<int>`.
+    Named(Ident<'source>, Vec<Ty<'source>>),
+    /// A reference: `&T` or `&mut T`, optionally with a lifetime, e.g., `&'a T`.
+    Ref(Option<Ident<'source>>, RefKind, Box<Ty<'source>>),
+}
+
+/// Whether a reference is shared or mutable.
+#[derive(Debug, Clone, Copy)]
+pub enum RefKind {
+    Shared,
+    Mut,
 }
 
 /// The AST representation of a dependent function type.
 #[derive(Debug)]
 pub struct FnDecl<'source> {
+    /// The lifetime parameters of the function, e.g., the `'a` in `fn<'a>(x: &'a int)`.
+    pub regions: Vec<Ident<'source>>,
     pub inputs: Vec<(Ident<'source>, Ty<'source>)>,
     pub output: Option<Ty<'source>>,
+    /// A predicate about the pointees of the mutable references after the call, e.g., the
+    /// `*x == old(*x) + 1` in `fn(x: &mut int) ensures *x == old(*x) + 1`.
+    pub ensures: Option<Predicate<'source>>,
     pub span: Span,
 }
@@ -1,3 +1,5 @@
+use crate::ast::IntTy;
+
 use logos::Logos;
 use std::fmt;
 
@@ -10,6 +12,9 @@ pub enum Token<'source> {
     /// The `int` token.
     #[token("int")]
     Int,
+    /// A token for the kinds of machine integers, e.g., `u8`.
+    #[regex("[iu](8|16|32|64|128|size)", |lex| lex.slice().parse())]
+    IntTy(IntTy),
     /// The `true` token.
     #[token("true")]
     True,
@@ -24,9 +29,21 @@ pub enum Token<'source> {
     /// It follows the same rules as the rust reference for valid identifiers.
     #[regex("[a-zA-Z][a-zA-Z0-9_]*|_[a-zA-Z0-9_]+")]
     Ident(&'source str),
+    /// A token for lifetime names, e.g., `'a`.
+    #[regex("'[a-zA-Z_][a-zA-Z0-9_]*")]
+    Lifetime(&'source str),
     /// The `fn` token.
     #[token("fn")]
     Fn,
+    /// The `mut` token.
+    #[token("mut")]
+    Mut,
+    /// The `ensures` token.
+    #[token("ensures")]
+    Ensures,
+    /// The `old` token.
+    #[token("old")]
+    Old,
     /// The `+` token.
     #[token("+")]
     Add,
@@ -45,6 +62,9 @@ pub enum Token<'source> {
     /// The `&&` token.
     #[token("&&")]
     And,
+    /// The `&` token.
+    #[token("&")]
+    Ampersand,
     /// The `||` token.
     #[token("||")]
     Or,
@@ -109,17 +129,23 @@ impl<'source> fmt::Display for Token<'source> {
         match self {
             Bool => "bool".fmt(f),
             Int => "int".fmt(f),
+            IntTy(int_ty) => int_ty.name().fmt(f),
             True => "true".fmt(f),
             False => "false".fmt(f),
             Integer(int) => write!(f, "{}", int),
             Ident(symbol) => symbol.fmt(f),
+            Lifetime(symbol) => symbol.fmt(f),
             Fn => "fn".fmt(f),
+            Mut => "mut".fmt(f),
+            Ensures => "ensures".fmt(f),
+            Old => "old".fmt(f),
             Add => "+".fmt(f),
             Sub => "-".fmt(f),
             Mul => "*".fmt(f),
             Div => "/".fmt(f),
             Rem => "%".fmt(f),
             And => "&&".fmt(f),
+            Ampersand => "&".fmt(f),
             Or => "||".fmt(f),
             Not => "!".fmt(f),
             Eq => "==".fmt(f),
@@ -2,7 +2,7 @@ pub mod ast;
 mod lexer;
 mod result;
 
-use ast::FnDecl;
+use ast::{FnDecl, Ty};
 use lexer::Lexer;
 pub use lexer::Token;
 pub use result::{ParseError, ParseErrorKind, ParseResult};
@@ -21,3 +21,11 @@ pub fn parse_fn_decl<'source>(source: &'source str) -> ParseResult<FnDecl<'sourc
         .parse(source, lexer)
         .map_err(ParseError::from)
 }
+
+/// Parse a type annotation, e.g., the type of a field of a struct.
+pub fn parse_ty<'source>(source: &'source str) -> ParseResult<Ty<'source>> {
+    let lexer = Lexer::new(source);
+    parser::TyParser::new()
+        .parse(source, lexer)
+        .map_err(ParseError::from)
+}
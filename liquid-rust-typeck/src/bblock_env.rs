@@ -27,14 +27,20 @@ impl BBlockEnv {
     ) -> Self {
         let mut ghost_vars = OrderedMap::new();
         let mut locals = vec![];
+        let depth = vars_in_scope.len();
         for (local, local_decl) in local_decls.iter_enumerated() {
             let fresh_gv = ghost_gen.fresh();
-            let ty = refiner.maybe_uninit(local_decl.ty, local, vars_in_scope);
+            let mut pointees = vec![];
+            let ty = refiner.maybe_uninit(local_decl.ty, local, vars_in_scope, &mut pointees);
+            // Pointees are bound before the references pointing to them.
+            for (gv, ty) in pointees {
+                ghost_vars.insert(gv, ty);
+            }
             ghost_vars.insert(fresh_gv, ty);
             vars_in_scope.push(Var::from(fresh_gv));
             locals.push((local, fresh_gv));
         }
-        vars_in_scope.truncate(vars_in_scope.len() - local_decls.len());
+        vars_in_scope.truncate(depth);
         BBlockEnv { ghost_vars, locals }
     }
 }
@@ -4,8 +4,8 @@ use liquid_rust_common::{
     index::{newtype_index, IndexVec},
     ordered_map::OrderedMap,
 };
-use liquid_rust_fixpoint::Constraint;
-use liquid_rust_lrir::ty::{embed::EmbeddingCtxt, Refine, Ty, Var};
+use liquid_rust_fixpoint::{Constraint, Pred, Sort};
+use liquid_rust_lrir::ty::{embed::EmbeddingCtxt, Path, Refine, Ty, TyKind, Var};
 
 newtype_index! {
     struct NodeId {
@@ -64,7 +64,12 @@ impl BindingTree {
     }
 
     pub fn push_pred(&mut self, refine: Refine) {
-        self.push_node(Node::Leaf(refine));
+        self.push_node(Node::Leaf(refine, None));
+    }
+
+    /// Push a predicate that is reported back by its `tag` if it doesn't hold.
+    pub fn push_tagged_pred(&mut self, refine: Refine, tag: String) {
+        self.push_node(Node::Leaf(refine, Some(tag)));
     }
 
     fn push_node(&mut self, node: Node) {
@@ -88,7 +93,10 @@ impl BindingTree {
         let node = &self.nodes[node_id];
 
         match node {
-            Node::Leaf(refine) => Some(Constraint::Pred(refine.embed(cx))),
+            Node::Leaf(refine, None) => Some(Constraint::Pred(refine.embed(cx))),
+            Node::Leaf(refine, Some(tag)) => {
+                Some(Constraint::Tagged(refine.embed(cx), tag.clone()))
+            }
             Node::Blank(children) => {
                 let conj = children
                     .iter()
@@ -97,41 +105,26 @@ impl BindingTree {
 
                 Constraint::join(conj)
             }
-            Node::Binding(var, ty, children) => match ty.kind() {
-                liquid_rust_lrir::ty::TyKind::Refined(base_ty, refinement) => {
-                    cx.push_var(Var::Nu);
-                    let refinement = refinement.embed(cx);
-                    cx.pop_var();
-
-                    let sort = base_ty.embed();
-
-                    cx.push_var(*var);
+            Node::Binding(var, ty, children) => {
+                let mut bound = vec![];
+                self.embed_binding(Path::from(*var), ty, cx, &mut bound);
 
-                    let conj = children
-                        .iter()
-                        .filter_map(|&node_id| self.gen_constraint_rec(node_id, cx))
-                        .collect();
+                let conj = children
+                    .iter()
+                    .filter_map(|&node_id| self.gen_constraint_rec(node_id, cx))
+                    .collect();
 
+                for _ in &bound {
                     cx.pop_var();
-
-                    Some(Constraint::ForAll(
-                        sort,
-                        refinement,
-                        Box::new(Constraint::join(conj)?),
-                    ))
                 }
 
-                liquid_rust_lrir::ty::TyKind::Tuple(_) => todo!(),
-                liquid_rust_lrir::ty::TyKind::Ref(_, _, _) => todo!(),
-                liquid_rust_lrir::ty::TyKind::Uninit(_size) => {
-                    let conj = children
-                        .iter()
-                        .filter_map(|&node_id| self.gen_constraint_rec(node_id, cx))
-                        .collect();
-
-                    Constraint::join(conj)
-                }
-            },
+                bound.into_iter().rev().try_fold(
+                    Constraint::join(conj)?,
+                    |constraint, (sort, refinement)| {
+                        Some(Constraint::ForAll(sort, refinement, Box::new(constraint)))
+                    },
+                )
+            }
             Node::Guard(refine, children) => {
                 let conj = children
                     .iter()
@@ -146,6 +139,48 @@ impl BindingTree {
         }
     }
 
+    /// Bind `path` to a value of type `ty` in `cx`. Tuples, structs and enums of a known variant
+    /// are flattened, binding a path for each of their fields. The sort and refinement of each bound path are pushed to `bound`.
+    fn embed_binding(
+        &self,
+        path: Path,
+        ty: &Ty,
+        cx: &mut EmbeddingCtxt,
+        bound: &mut Vec<(Sort, Pred)>,
+    ) {
+        match ty.kind() {
+            TyKind::Refined(base_ty, refinement) => {
+                cx.push_var(Var::Nu);
+                let refinement = match base_ty.embed_invariant(&Var::Nu, cx) {
+                    // Every binding assumes the invariant of its type, e.g., the range of an
+                    // integer.
+                    Some(invariant) => Pred::And(vec![invariant, refinement.embed(cx)]),
+                    None => refinement.embed(cx),
+                };
+                cx.pop_var();
+
+                cx.push_path(path);
+                bound.push((base_ty.embed(), refinement));
+            }
+            TyKind::Tuple(tup) | TyKind::Adt(_, tup) => {
+                for (i, (fld, ty)) in tup.iter().enumerate() {
+                    self.embed_binding(path.extend(i), ty, cx, bound);
+                    cx.push_field(*fld, path.extend(i));
+                }
+                for _ in 0..tup.len() {
+                    cx.pop_field();
+                }
+            }
+            // The fields of an enum are only bound while its variant is known.
+            TyKind::Enum(_, Some(variant), variants) => {
+                self.embed_binding(path.extend(*variant), &variants[*variant], cx, bound);
+            }
+            TyKind::Enum(_, None, _) => {}
+            // The pointee of a reference is bound to a ghost variable of its own.
+            TyKind::Ref(..) | TyKind::Uninit(_) => {}
+        }
+    }
+
     pub fn dot<W: std::io::Write>(&self, mut buf: W) -> std::io::Result<()> {
         writeln!(buf, "digraph g {{")?;
 
@@ -156,7 +191,7 @@ impl BindingTree {
                 Node::Blank(..) => writeln!(buf, "    label = \"blank\"")?,
                 Node::Binding(var, ty, ..) => writeln!(buf, "    label = \"{}: {}\"", var, ty)?,
                 Node::Guard(refine, ..) => writeln!(buf, "    label = \"{}\"", refine)?,
-                Node::Leaf(refine) => writeln!(buf, "    label = \"{}\"", refine)?,
+                Node::Leaf(refine, ..) => writeln!(buf, "    label = \"{}\"", refine)?,
             }
 
             writeln!(buf, "  ];")?;
@@ -182,7 +217,7 @@ enum Node {
     Blank(Vec<NodeId>),
     Binding(Var, Ty, Vec<NodeId>),
     Guard(Refine, Vec<NodeId>),
-    Leaf(Refine),
+    Leaf(Refine, Option<String>),
 }
 
 impl Node {
@@ -191,7 +226,7 @@ impl Node {
             Node::Blank(children) => children.iter(),
             Node::Binding(_, _, children) => children.iter(),
             Node::Guard(_, children) => children.iter(),
-            Node::Leaf(_) => [].iter(),
+            Node::Leaf(..) => [].iter(),
         };
         iter.copied()
     }
@@ -201,7 +236,7 @@ impl Node {
             Node::Blank(children) => children,
             Node::Binding(_, _, children) => children,
             Node::Guard(_, children) => children,
-            Node::Leaf(_) => panic!("Trying to push a child into a leaf node."),
+            Node::Leaf(..) => panic!("Trying to push a child into a leaf node."),
         };
         children.push(child);
     }
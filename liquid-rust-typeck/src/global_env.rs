@@ -1,20 +1,100 @@
-use std::collections::HashMap;
+use std::{borrow::Cow, collections::HashMap, iter};
 
-use liquid_rust_lrir::ty::FnSig;
+use itertools::Itertools;
+use liquid_rust_lrir::ty::{self, refiner::Refiner, FnSig};
 use rustc_hir::def_id::DefId;
-use rustc_middle::ty::{subst::SubstsRef, TyCtxt};
+use rustc_middle::ty::{
+    subst::{Subst, SubstsRef},
+    TyCtxt, TyKind,
+};
 
 pub struct GlobalEnv<'tcx> {
-    _tcx: TyCtxt<'tcx>,
+    tcx: TyCtxt<'tcx>,
     pub sigs: HashMap<DefId, FnSig>,
+    /// Specifications for functions of other crates, indexed by [path](Self::spec_path).
+    extern_sigs: HashMap<String, FnSig>,
 }
 
 impl<'tcx> GlobalEnv<'tcx> {
-    pub fn new(_tcx: TyCtxt<'tcx>, sigs: HashMap<DefId, FnSig>) -> Self {
-        Self { _tcx, sigs }
+    pub fn new(
+        tcx: TyCtxt<'tcx>,
+        sigs: HashMap<DefId, FnSig>,
+        extern_sigs: HashMap<String, FnSig>,
+    ) -> Self {
+        Self {
+            tcx,
+            sigs,
+            extern_sigs,
+        }
     }
 
-    pub fn fn_sig(&self, def_id: DefId, _substs: SubstsRef<'tcx>) -> &FnSig {
-        &self.sigs[&def_id]
+    pub fn tcx(&self) -> TyCtxt<'tcx> {
+        self.tcx
+    }
+
+    /// Returns the refined signature of the function `def_id` instantiated with `substs`.
+    /// Functions without a refined signature or a specification get a
+    /// [default](Refiner::default_fn_sig) one computed from their Rust signature.
+    pub fn fn_sig(
+        &self,
+        def_id: DefId,
+        substs: SubstsRef<'tcx>,
+        lr_tcx: &ty::TyCtxt,
+    ) -> Cow<FnSig> {
+        if let Some(fn_sig) = self.sigs.get(&def_id) {
+            return Cow::Borrowed(fn_sig);
+        }
+
+        // Late-bound regions are left bound, the default signature only needs to tell them apart.
+        let fn_sig = self
+            .tcx
+            .fn_sig(def_id)
+            .subst(self.tcx, substs)
+            .skip_binder();
+
+        // The specification of a generic function only applies to the instances with the same
+        // shape, e.g., a specification for `max` over integers doesn't apply to `max::<bool>`.
+        let extern_sig = self
+            .extern_sigs
+            .get(&self.spec_path(def_id))
+            .and_then(|extern_sig| extern_sig.with_int_tys(fn_sig, lr_tcx));
+
+        match extern_sig {
+            Some(extern_sig) => Cow::Owned(extern_sig),
+            None => Cow::Owned(Refiner::default_fn_sig(lr_tcx, fn_sig)),
+        }
+    }
+
+    /// Returns the path identifying `def_id` in spec files, e.g., `core::cmp::max`. Inherent
+    /// methods are prefixed by the type of their `impl` instead, e.g., `i32::signum` or
+    /// `my_crate::Point::norm`.
+    pub fn spec_path(&self, def_id: DefId) -> String {
+        let tcx = self.tcx;
+
+        if let Some(impl_def_id) = tcx.impl_of_method(def_id) {
+            if tcx.trait_id_of_impl(impl_def_id).is_none() {
+                let self_ty = tcx.type_of(impl_def_id);
+                let prefix = match self_ty.kind() {
+                    TyKind::Adt(adt_def, _) => self.def_path(adt_def.did),
+                    _ => self_ty.to_string(),
+                };
+                return format!("{}::{}", prefix, tcx.item_name(def_id));
+            }
+        }
+
+        self.def_path(def_id)
+    }
+
+    /// Returns the path of `def_id` starting with the name of its crate.
+    fn def_path(&self, def_id: DefId) -> String {
+        let tcx = self.tcx;
+        let crate_name = tcx.crate_name(def_id.krate).to_string();
+        let segments = tcx
+            .def_path(def_id)
+            .data
+            .into_iter()
+            .map(|segment| segment.data.to_string());
+
+        iter::once(crate_name).chain(segments).join("::")
     }
 }
@@ -16,23 +16,26 @@ use global_env::GlobalEnv;
 use itertools::Itertools;
 use local_env::LocalEnv;
 
-use liquid_rust_common::index::{Idx, IndexGen, IndexVec};
+use liquid_rust_common::{
+    config::{self, IntModel},
+    index::{Idx, IndexGen, IndexVec},
+};
 use liquid_rust_fixpoint::{Fixpoint, Safeness};
 use liquid_rust_lrir::{
     mir::{
-        BasicBlock, BinOp, Body, Local, Operand, PlaceRef, Rvalue, Statement, StatementKind,
-        Terminator, TerminatorKind, UnOp,
+        AggregateKind, AssertKind, BasicBlock, BinOp, Body, Local, Operand, Place, PlaceElem,
+        PlaceRef, Rvalue, Span, Statement, StatementKind, Terminator, TerminatorKind, UnOp,
     },
     ty::{
-        self, refiner::Refiner, subst::Subst, BaseTy, FnSig, KVid, Path, Pred, Ty, TyCtxt, TyKind,
-        Var,
+        self, refiner::Refiner, subst::Subst, BaseTy, Field, FnSig, GhostVar, IntTy, KVid, Path,
+        Pred, Region, Ty, TyCtxt, TyKind, Var,
     },
 };
 
 use rustc_middle::mir;
 use rustc_mir::dataflow::{self, impls::MaybeUninitializedPlaces, move_paths::MoveData};
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 pub struct Checker<'tcx, 'a> {
     tcx: &'a TyCtxt,
@@ -70,10 +73,19 @@ impl<'tcx, 'a> Checker<'tcx, 'a> {
         let ret_ty = Refiner::uninit(tcx, task.body.local_decls[ret_place].ty);
         env.alloc(ret_place, ret_ty);
 
+        // Without borrow checker facts, every block gets the same flow-insensitive approximation.
+        let inferred_regions = match task.regions {
+            Some(_) => HashMap::new(),
+            None => infer_regions(task.body, task.fn_decl, task.global_env, tcx),
+        };
         let bb_envs = (0..task.body.basic_blocks.len())
             .map(|i| {
                 let bb = mir::BasicBlock::from_usize(i);
-                let refiner = task.refiner_for_block(tcx, &kvid_gen, bb);
+                let regions = task
+                    .regions
+                    .as_ref()
+                    .map_or(&inferred_regions, |regions| &regions[bb]);
+                let refiner = task.refiner_for_block(tcx, &kvid_gen, &ghost_gen, regions, bb);
                 BBlockEnv::new(
                     &task.body.local_decls,
                     refiner,
@@ -83,15 +95,8 @@ impl<'tcx, 'a> Checker<'tcx, 'a> {
             })
             .collect();
 
-        let ret_env = BBlockEnv {
-            ghost_vars: task
-                .fn_decl
-                .ensures
-                .iter()
-                .map(|(gv, ty)| (*gv, subst.apply(ty, tcx)))
-                .collect(),
-            locals: vec![(ret_place, subst.apply(&task.fn_decl.output, tcx))],
-        };
+        let args = task.body.args_iter().collect::<Vec<_>>();
+        let ret_env = ret_env(task.fn_decl, &args, &subst, tcx);
 
         let local_gen = IndexGen::new();
         local_gen.skip(task.body.local_decls.len());
@@ -118,11 +123,50 @@ impl<'tcx, 'a> Checker<'tcx, 'a> {
         let file = std::fs::File::create("binding_tree.dot").unwrap();
         env.bindings.dot(file).unwrap();
 
-        let constraint = env.bindings.gen_constraint();
-        match Fixpoint::default().check(constraint).tag {
-            Safeness::Safe => CheckingResult { ok: true },
-            _ => CheckingResult { ok: false },
+        check_constraint(&env, Some(task.body))
+    }
+
+    /// Check that `sub` refines `sup`, i.e., that the precondition of `sub` is weaker than the
+    /// precondition of `sup` and that its postcondition is stronger. This is used to check that the
+    /// implementation of a trait method satisfies the specification of the trait.
+    pub fn check_fn_sig_subtyping(sub: &FnSig, sup: &FnSig, tcx: &TyCtxt) -> CheckingResult {
+        let ghost_gen = IndexGen::new();
+        let mut env = LocalEnv::new(tcx, &ghost_gen);
+
+        // Assume the precondition of `sup` for some fresh arguments.
+        let mut subst = Subst::new();
+        for (gv, ty) in &sup.requires {
+            let fresh_gv = env.fresh_ghost();
+            env.push_binding(fresh_gv, subst.apply(ty, tcx));
+            subst.add_ghost_var_subst(*gv, fresh_gv);
+        }
+        let args = sup
+            .inputs
+            .iter()
+            .enumerate()
+            .map(|(i, gv)| {
+                let local = Local::new(i + 1);
+                env.insert_local(local, subst.apply(gv, tcx));
+                local
+            })
+            .collect::<Vec<_>>();
+
+        // Calling `sub` with those arguments must satisfy its precondition.
+        let (in_env, out_env, ret, updated) = env.open_fn_sig(sub, &args);
+        env.env_subtyping(&in_env);
+        env.extend(out_env);
+
+        for (local, ty) in updated {
+            env.update(deref(local).as_ref(), ty);
         }
+
+        // And its result must satisfy the postcondition of `sup`.
+        env.alloc(Local::new(0), ret);
+        let ret_env = ret_env(sup, &args, &subst, tcx);
+        let subst = env.infer_jump_subst(&ret_env);
+        env.env_subtyping(&subst.apply(&ret_env, tcx));
+
+        check_constraint(&env, None)
     }
 
     fn check_basic_block(
@@ -140,17 +184,40 @@ impl<'tcx, 'a> Checker<'tcx, 'a> {
                 for statement in &bb_data.statements {
                     self.check_statement(statement, env);
                 }
-                self.check_terminator(&bb_data.terminator, env, seen);
+                self.check_terminator(bb, &bb_data.terminator, env, seen);
             });
         }
     }
 
     fn check_statement(&self, statement: &Statement, env: &mut LocalEnv) {
         match &statement.kind {
+            // An enum is built like in optimized MIR, i.e., by writing the fields of the variant
+            // and then setting the discriminant, so the other variants keep the types they have
+            // in the place.
+            StatementKind::Assign(
+                place,
+                Rvalue::Aggregate(AggregateKind::Variant(variant), ops),
+            ) => {
+                let mut tys = Vec::with_capacity(ops.len());
+                for op in ops {
+                    tys.push(self.check_operand(op, env).1);
+                }
+                for (i, ty) in tys.into_iter().enumerate() {
+                    let mut field = place.clone();
+                    field.projection.push(PlaceElem::Downcast(*variant));
+                    field.projection.push(PlaceElem::Field(i));
+                    env.update(field.as_ref(), ty);
+                }
+                env.set_discriminant(place.as_ref(), *variant);
+            }
             StatementKind::Assign(place, rvalue) => {
                 // FIXME: check ownership safety
                 let ty = self.check_rvalue(rvalue, env);
                 env.update(place.as_ref(), ty);
+                env.check_invariants(place.as_ref());
+            }
+            StatementKind::SetDiscriminant(place, variant) => {
+                env.set_discriminant(place.as_ref(), *variant);
             }
             StatementKind::StorageLive(_) | StatementKind::StorageDead(_) | StatementKind::Nop => {}
         }
@@ -158,6 +225,7 @@ impl<'tcx, 'a> Checker<'tcx, 'a> {
 
     fn check_terminator(
         &self,
+        bb: BasicBlock,
         terminator: &Terminator<'tcx>,
         env: &mut LocalEnv,
         seen: &mut HashSet<BasicBlock>,
@@ -166,25 +234,73 @@ impl<'tcx, 'a> Checker<'tcx, 'a> {
 
         let tcx = self.tcx;
         match &terminator.kind {
-            TerminatorKind::Goto { target } | TerminatorKind::Assert { target, .. } => {
+            TerminatorKind::Goto { target } => {
                 self.check_goto_or_inline(*target, env, seen);
             }
+            TerminatorKind::Assert {
+                cond,
+                expected,
+                target,
+                ..
+            } => {
+                let cond = self.check_operand(cond, env).0;
+                let assertion = if *expected {
+                    cond
+                } else {
+                    tcx.mk_un_op(ty::UnOp::Not, cond)
+                };
+                // The assertion is tagged with its block so a failure can be traced back to it.
+                if config::panic_freedom() {
+                    env.assert(assertion.clone(), bb.index().to_string());
+                }
+                // Execution only continues if the assertion holds.
+                env.with_guard(assertion, |env| {
+                    self.check_goto_or_inline(*target, env, seen);
+                });
+            }
             TerminatorKind::SwitchInt {
                 discr,
                 switch_ty,
                 targets,
             } => {
+                let enum_place = self.switched_enum(bb, discr);
                 let (discr, ty) = self.check_operand(discr, env);
                 assert!(matches!(ty.kind(), TyKind::Refined(bty, ..) if bty == switch_ty));
-                for (bits, target) in targets.iter() {
+                // A switch on the discriminant of an enum narrows the enum to the variant of each
+                // branch. The branches for other variants than the one the enum is known to be
+                // are never taken.
+                let (known, n_variants) = match enum_place.map(|place| env.lookup(place.as_ref())) {
+                    Some(ty) => match ty.kind() {
+                        TyKind::Enum(_, known, variants) => (*known, variants.len()),
+                        _ => unreachable!("{} is not an enum", ty),
+                    },
+                    None => (None, 0),
+                };
+                let is_taken = |bits: u128| known.map_or(true, |variant| variant as u128 == bits);
+                for (bits, target) in targets.iter().filter(|(bits, _)| is_taken(*bits)) {
                     let constant = tcx.mk_const_from_bits(bits, *switch_ty);
                     let guard = tcx.mk_bin_op(ty::BinOp::Eq, discr.clone(), constant);
                     env.with_guard(guard, |env| {
+                        if let Some(place) = enum_place {
+                            env.downcast(place.as_ref(), bits as usize);
+                        }
                         // If the target only has one predecessor (i.e. this one),
                         // we can just check it without checking the goto.
                         self.check_goto_or_inline(target, env, seen);
                     });
                 }
+                // The otherwise branch is for the variants left, and it narrows the enum if there
+                // is only one.
+                let others = (0..n_variants)
+                    .filter(|variant| targets.iter().all(|(bits, _)| bits != *variant as u128))
+                    .collect::<Vec<_>>();
+                if known.map_or(false, |variant| !others.contains(&variant)) {
+                    return;
+                }
+                let otherwise = match others[..] {
+                    [variant] => Some(variant),
+                    _ => None,
+                };
                 let guard = targets
                     .iter()
                     .map(|(bits, _)| {
@@ -197,31 +313,54 @@ impl<'tcx, 'a> Checker<'tcx, 'a> {
                     .fold1(|p1, p2| tcx.mk_bin_op(ty::BinOp::And, p1, p2))
                     .unwrap_or(tcx.preds.tt());
                 env.with_guard(guard, |env| {
+                    if let (Some(place), Some(variant)) = (enum_place, otherwise) {
+                        env.downcast(place.as_ref(), variant);
+                    }
                     self.check_goto_or_inline(targets.otherwise(), env, seen);
                 });
             }
             TerminatorKind::Return => {
                 self.check_goto(&self.ret_env, env);
             }
+            // Rustc guarantees that this is never executed, so there is nothing to prove.
+            TerminatorKind::Unreachable => {}
+            TerminatorKind::Resume => unreachable!("cleanup blocks are never checked"),
             TerminatorKind::Call {
                 func: (def_id, substs),
                 args,
                 destination,
             } => {
-                let fn_sig = self.global_env.fn_sig(*def_id, substs);
+                let fn_sig = self.global_env.fn_sig(*def_id, substs, self.tcx);
                 let args = args
                     .iter()
                     .map(|arg| {
                         // ANF normalization on-the-fly
                         let local = self.local_gen.fresh();
                         let ty = self.check_operand(arg, env).1;
+                        // A reference argument passed on is reborrowed, so it points to the memory
+                        // of the caller through the argument like the other references, and the
+                        // regions passed for the same lifetime can be joined.
+                        let ty = match (arg, ty.kind()) {
+                            (
+                                Operand::Copy(place) | Operand::Move(place),
+                                TyKind::Ref(bk, Region::Abstract(_), gv),
+                            ) if place.projection.is_empty() => {
+                                self.tcx.mk_ref(*bk, deref(place.local), *gv)
+                            }
+                            _ => ty,
+                        };
                         env.alloc(local, ty);
                         local
                     })
                     .collect::<Vec<_>>();
-                let (in_env, out_env, ret) = env.open_fn_sig(fn_sig, &args);
+                let (in_env, out_env, ret, updated) = env.open_fn_sig(&fn_sig, &args);
                 env.env_subtyping(&in_env);
                 env.extend(out_env);
+                // The callee may have changed the pointees of mutable references as its signature
+                // says.
+                for (local, ty) in updated {
+                    env.update(deref(local).as_ref(), ty);
+                }
                 if let Some((place, bb)) = destination {
                     env.update(place.as_ref(), ret);
                     self.check_goto(&self.bb_envs[*bb], env);
@@ -243,6 +382,34 @@ impl<'tcx, 'a> Checker<'tcx, 'a> {
         }
     }
 
+    /// The enum whose discriminant is the operand `discr` of the switch terminating `bb`, if
+    /// any, i.e., the place `p` in a `discr = discriminant(p)` statement of `bb`.
+    fn switched_enum(&self, bb: BasicBlock, discr: &Operand) -> Option<&'a Place> {
+        let local = match discr {
+            Operand::Copy(place) | Operand::Move(place) if place.projection.is_empty() => {
+                place.local
+            }
+            _ => return None,
+        };
+        let body: &'a Body<'tcx> = self.body;
+        let rvalue = body.basic_blocks[bb]
+            .statements
+            .iter()
+            .rev()
+            .find_map(|statement| match &statement.kind {
+                StatementKind::Assign(place, rvalue)
+                    if place.local == local && place.projection.is_empty() =>
+                {
+                    Some(rvalue)
+                }
+                _ => None,
+            })?;
+        match rvalue {
+            Rvalue::Discriminant(place, _) => Some(place),
+            _ => None,
+        }
+    }
+
     fn check_goto(&self, bb_env: &BBlockEnv, env: &mut LocalEnv) {
         let subst = env.infer_jump_subst(bb_env);
         let bb_env = subst.apply(bb_env, self.tcx);
@@ -252,7 +419,7 @@ impl<'tcx, 'a> Checker<'tcx, 'a> {
                 .tcx
                 .selfify(&env.lookup(PlaceRef::from(local)), Path::from(gv));
             let ty2 = &bb_env.ghost_vars[&gv];
-            env.subtyping(ty1, ty2);
+            env.subtyping(ty1, ty2, &bb_env);
         }
     }
 
@@ -260,10 +427,16 @@ impl<'tcx, 'a> Checker<'tcx, 'a> {
         let tcx = self.tcx;
         match rvalue {
             Rvalue::Use(op) => self.check_operand(op, env).1,
-            Rvalue::Ref(_, _, _) => todo!(),
+            Rvalue::Ref(region, bk, place) => {
+                env.downcast_projections(place.as_ref());
+                let gv = env.borrow(place.as_ref());
+                tcx.mk_ref(*bk, region.clone(), gv)
+            }
             Rvalue::BinaryOp(bin_op, op1, op2) => {
                 let (op1, ty1) = self.check_operand(op1, env);
                 let (op2, ty2) = self.check_operand(op2, env);
+                // Bitwise operators on booleans are the logical ones.
+                let is_bool = ty1.is_bool();
                 let ty_bin_op = match bin_op {
                     BinOp::Add => Some(ty::BinOp::Add),
                     BinOp::Sub => Some(ty::BinOp::Sub),
@@ -276,12 +449,15 @@ impl<'tcx, 'a> Checker<'tcx, 'a> {
                     BinOp::Mul => Some(ty::BinOp::Mul),
                     BinOp::Div => Some(ty::BinOp::Div),
                     BinOp::Rem => Some(ty::BinOp::Rem),
-                    BinOp::BitXor
-                    | BinOp::BitAnd
-                    | BinOp::BitOr
-                    | BinOp::Shl
-                    | BinOp::Shr
-                    | BinOp::Offset => None,
+                    BinOp::BitXor if is_bool => Some(ty::BinOp::Neq),
+                    BinOp::BitAnd if is_bool => Some(ty::BinOp::And),
+                    BinOp::BitOr if is_bool => Some(ty::BinOp::Or),
+                    BinOp::BitXor => Some(ty::BinOp::BitXor),
+                    BinOp::BitAnd => Some(ty::BinOp::BitAnd),
+                    BinOp::BitOr => Some(ty::BinOp::BitOr),
+                    BinOp::Shl => Some(ty::BinOp::Shl),
+                    BinOp::Shr => Some(ty::BinOp::Shr),
+                    BinOp::Offset => None,
                 };
                 let ret_ty = match bin_op {
                     BinOp::Add
@@ -289,13 +465,14 @@ impl<'tcx, 'a> Checker<'tcx, 'a> {
                     | BinOp::Mul
                     | BinOp::Div
                     | BinOp::Rem
-                    | BinOp::BitXor
-                    | BinOp::BitAnd
-                    | BinOp::BitOr
                     | BinOp::Shl
                     | BinOp::Shr => {
                         assert!(ty1.is_int() && ty2.is_int());
-                        BaseTy::Int
+                        ty1.base_ty().unwrap()
+                    }
+                    BinOp::BitXor | BinOp::BitAnd | BinOp::BitOr => {
+                        assert!(ty1.is_int() && ty2.is_int() || is_bool && ty2.is_bool());
+                        ty1.base_ty().unwrap()
                     }
                     BinOp::Lt | BinOp::Gt | BinOp::Le | BinOp::Ge => {
                         assert!(ty1.is_int() && ty2.is_int());
@@ -305,17 +482,124 @@ impl<'tcx, 'a> Checker<'tcx, 'a> {
                     BinOp::Eq | BinOp::Ne => BaseTy::Bool,
                     BinOp::Offset => todo!(),
                 };
-                let refine = if let Some(ty_bin_op) = ty_bin_op {
-                    tcx.mk_bin_op(
-                        ty::BinOp::Eq,
-                        tcx.preds.nu(),
-                        tcx.mk_bin_op(ty_bin_op, op1, op2),
-                    )
-                } else {
-                    tcx.preds.tt()
+                // The result of a division by zero is unconstrained in fixpoint, so the divisor
+                // must be proved non-zero, e.g., by the check rustc inserts before the division.
+                if matches!(bin_op, BinOp::Div | BinOp::Rem) {
+                    env.require(tcx.mk_bin_op(ty::BinOp::Neq, op2.clone(), tcx.mk_int(false, 0)));
+                }
+                let refine = match (ty_bin_op, ret_ty) {
+                    (Some(ty_bin_op), BaseTy::Int(int_ty)) if ty_bin_op.is_uninterpreted() => {
+                        tcx.mk_bitwise_result(ty_bin_op, int_ty, op1, op2)
+                    }
+                    (Some(ty_bin_op), _) => {
+                        let value = tcx.mk_bin_op(ty_bin_op, op1, op2);
+                        let value = match (bin_op, ret_ty) {
+                            (BinOp::Add | BinOp::Sub | BinOp::Mul, BaseTy::Int(int_ty)) => {
+                                self.unchecked_result(int_ty, value, env)
+                            }
+                            _ => value,
+                        };
+                        tcx.mk_bin_op(ty::BinOp::Eq, tcx.preds.nu(), value)
+                    }
+                    (None, _) => tcx.preds.tt(),
                 };
                 tcx.mk_refine(ret_ty, refine)
             }
+            Rvalue::CheckedBinaryOp(bin_op, op1, op2) => {
+                let (op1, ty1) = self.check_operand(op1, env);
+                let (op2, ty2) = self.check_operand(op2, env);
+                assert!(ty1.is_int() && ty2.is_int());
+                let int_ty = match ty1.base_ty() {
+                    Some(BaseTy::Int(int_ty)) => int_ty,
+                    _ => unreachable!("{}", ty1),
+                };
+
+                let (refine, overflow) = match bin_op {
+                    BinOp::Add | BinOp::Sub | BinOp::Mul => {
+                        let ty_bin_op = match bin_op {
+                            BinOp::Add => ty::BinOp::Add,
+                            BinOp::Sub => ty::BinOp::Sub,
+                            _ => ty::BinOp::Mul,
+                        };
+                        let value = tcx.mk_bin_op(ty_bin_op, op1, op2);
+                        let overflow =
+                            tcx.mk_un_op(ty::UnOp::Not, tcx.mk_in_range(int_ty, value.clone()));
+                        let refine = tcx.mk_bin_op(ty::BinOp::Eq, tcx.preds.nu(), value);
+                        (refine, overflow)
+                    }
+                    // A shift overflows if the shift amount is not smaller than the width.
+                    BinOp::Shl | BinOp::Shr => {
+                        let width = tcx.mk_const(ty::Constant::Int(int_ty.bit_width().into()));
+                        let overflow = tcx.mk_bin_op(
+                            ty::BinOp::Or,
+                            tcx.mk_bin_op(ty::BinOp::Lt, op2.clone(), tcx.mk_int(false, 0)),
+                            tcx.mk_bin_op(ty::BinOp::Gte, op2.clone(), width),
+                        );
+                        let ty_bin_op = if *bin_op == BinOp::Shl {
+                            ty::BinOp::Shl
+                        } else {
+                            ty::BinOp::Shr
+                        };
+                        let refine = tcx.mk_bitwise_result(ty_bin_op, int_ty, op1, op2);
+                        (refine, overflow)
+                    }
+                    _ => unreachable!("unexpected checked operation: {:?}", bin_op),
+                };
+
+                // The result is only known if the operation didn't overflow.
+                let result = tcx.mk_bin_op(ty::BinOp::Or, overflow.clone(), refine);
+                let overflowed = tcx.mk_bin_op(ty::BinOp::Eq, tcx.preds.nu(), overflow);
+
+                tcx.mk_tuple(
+                    vec![
+                        (
+                            Field::from_usize(0),
+                            tcx.mk_refine(BaseTy::Int(int_ty), result),
+                        ),
+                        (
+                            Field::from_usize(1),
+                            tcx.mk_refine(BaseTy::Bool, overflowed),
+                        ),
+                    ]
+                    .into_iter()
+                    .collect(),
+                )
+            }
+            Rvalue::Aggregate(AggregateKind::Tuple, ops) => {
+                let tup = ops
+                    .iter()
+                    .enumerate()
+                    .map(|(i, op)| (Field::from_usize(i), self.check_operand(op, env).1))
+                    .collect();
+                tcx.mk_tuple(tup)
+            }
+            // A struct must satisfy its invariant as soon as it is built. It can be refined, as its
+            // values would have been rejected when lowering otherwise.
+            Rvalue::Aggregate(AggregateKind::Adt(def_id), ops) => {
+                let tup = ops
+                    .iter()
+                    .enumerate()
+                    .map(|(i, op)| (Field::from_usize(i), self.check_operand(op, env).1))
+                    .collect();
+                let ty = tcx.mk_adt(tcx.adt_def(*def_id).unwrap(), tup);
+                env.check_invariant(&ty);
+                ty
+            }
+            Rvalue::Aggregate(AggregateKind::Variant(_), _) => {
+                unreachable!("enums are built by `check_statement`")
+            }
+            Rvalue::Discriminant(place, bty) => {
+                let ty = env.lookup(place.as_ref());
+                match ty.kind() {
+                    // The discriminant of a variant is its index.
+                    TyKind::Enum(_, Some(variant), _) => {
+                        let discr = tcx.mk_const(ty::Constant::Int(*variant as u128));
+                        tcx.mk_refine(*bty, tcx.mk_bin_op(ty::BinOp::Eq, tcx.preds.nu(), discr))
+                    }
+                    TyKind::Enum(_, None, _) => tcx.mk_refine(*bty, tcx.preds.tt()),
+                    _ => unreachable!("{} is not an enum", ty),
+                }
+            }
             Rvalue::UnaryOp(un_op, op) => {
                 let (op, ty) = self.check_operand(op, env);
                 let (ret_ty, un_op) = match un_op {
@@ -325,17 +609,39 @@ impl<'tcx, 'a> Checker<'tcx, 'a> {
                     }
                     UnOp::Neg => {
                         assert!(ty.is_int());
-                        (BaseTy::Int, ty::UnOp::Neg)
+                        (ty.base_ty().unwrap(), ty::UnOp::Neg)
                     }
                 };
-                let pred = tcx.mk_bin_op(ty::BinOp::Eq, tcx.preds.nu(), tcx.mk_un_op(un_op, op));
+                let value = tcx.mk_un_op(un_op, op);
+                let value = match ret_ty {
+                    BaseTy::Int(int_ty) => self.unchecked_result(int_ty, value, env),
+                    _ => value,
+                };
+                let pred = tcx.mk_bin_op(ty::BinOp::Eq, tcx.preds.nu(), value);
                 tcx.mk_refine(ret_ty, pred)
             }
         }
     }
 
+    /// The result of an arithmetic operation on integers of kind `int_ty` whose mathematical
+    /// result is `value`, when rustc doesn't check it for overflow, e.g., in release builds.
+    /// Such an operation wraps around at runtime, so unless that is the integer model, it must be
+    /// proved not to overflow.
+    fn unchecked_result(&self, int_ty: IntTy, value: Pred, env: &mut LocalEnv) -> Pred {
+        match config::int_model() {
+            IntModel::Wrapping => self.tcx.mk_wrapping(int_ty, value),
+            IntModel::Overflow => {
+                env.require(self.tcx.mk_in_range(int_ty, value.clone()));
+                value
+            }
+        }
+    }
+
     fn check_operand(&self, op: &Operand, env: &mut LocalEnv) -> (Pred, Ty) {
         let tcx = self.tcx;
+        if let Operand::Copy(place) | Operand::Move(place) = op {
+            env.downcast_projections(place.as_ref());
+        }
         match op {
             Operand::Copy(place) => {
                 let ty = env.lookup(place.as_ref());
@@ -364,12 +670,206 @@ impl<'tcx, 'a> Checker<'tcx, 'a> {
     }
 }
 
+/// Check the constraint generated for `env`. Failed assertions are traced back to the `Assert`
+/// terminators of `body` they were generated for.
+fn check_constraint(env: &LocalEnv, body: Option<&Body>) -> CheckingResult {
+    let constraint = env.bindings.gen_constraint();
+    let result = Fixpoint::default().check(constraint);
+
+    let mut failed_asserts = vec![];
+    if let Some(body) = body {
+        for tag in result.failed_tags() {
+            let bb = match tag.parse() {
+                Ok(bb) => BasicBlock::from_usize(bb),
+                Err(_) => continue,
+            };
+            let terminator = &body.basic_blocks[bb].terminator;
+            if let TerminatorKind::Assert { kind, .. } = terminator.kind {
+                failed_asserts.push((terminator.span, kind));
+            }
+        }
+    }
+
+    CheckingResult {
+        ok: result.tag == Safeness::Safe,
+        failed_asserts,
+    }
+}
+
+/// The environment a function with signature `fn_sig` and arguments `args` must satisfy when it
+/// returns: the output must have its type and the pointees of mutable references their updated
+/// types. The ghost variables in the requirements of `fn_sig` are renamed by `subst`.
+fn ret_env(fn_sig: &FnSig, args: &[Local], subst: &Subst, tcx: &TyCtxt) -> BBlockEnv {
+    let mut ret_env = BBlockEnv {
+        ghost_vars: fn_sig
+            .ensures
+            .iter()
+            .map(|(gv, ty)| (*gv, subst.apply(ty, tcx)))
+            .collect(),
+        locals: vec![(Local::new(0), subst.apply(&fn_sig.output, tcx))],
+    };
+    for (i, pointee) in &fn_sig.outputs {
+        let gv = &fn_sig.inputs[*i];
+        if let TyKind::Ref(bk, region, _) = fn_sig.requires[gv].kind() {
+            let ty = tcx.mk_ref(*bk, region.clone(), *pointee);
+            ret_env
+                .ghost_vars
+                .insert(subst.apply(gv, tcx), subst.apply(&ty, tcx));
+            ret_env.locals.push((args[*i], subst.apply(gv, tcx)));
+        }
+    }
+    ret_env
+}
+
+/// The place `*local`.
+fn deref(local: Local) -> Place {
+    Place {
+        local,
+        projection: vec![PlaceElem::Deref],
+    }
+}
+
+/// Approximate the region of every local holding a reference by the places borrowed into it
+/// anywhere in `body`, following the references that are copied or moved between locals or returned
+/// by calls. Arguments start with the regions in `fn_decl`. Used when there are no borrow checker
+/// facts for `body`.
+fn infer_regions<'tcx>(
+    body: &Body<'tcx>,
+    fn_decl: &FnSig,
+    global_env: &GlobalEnv<'tcx>,
+    tcx: &TyCtxt,
+) -> HashMap<Local, Region> {
+    let mut regions = HashMap::new();
+    for (local, gv) in body.args_iter().zip(&fn_decl.inputs) {
+        if let TyKind::Ref(_, region, _) = fn_decl.requires[gv].kind() {
+            regions.insert(local, region.clone());
+        }
+    }
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for bb_data in body.basic_blocks.iter() {
+            let mut assigned = bb_data
+                .statements
+                .iter()
+                .filter_map(|statement| assigned_region(statement, &regions))
+                .collect::<Vec<_>>();
+            assigned.extend(returned_region(
+                &bb_data.terminator,
+                &regions,
+                global_env,
+                tcx,
+            ));
+            for (place, region) in assigned {
+                let region = match regions.get(&place.local) {
+                    Some(old_region) => old_region.join(&region),
+                    None => region,
+                };
+                if regions.get(&place.local) != Some(&region) {
+                    regions.insert(place.local, region);
+                    changed = true;
+                }
+            }
+        }
+    }
+    regions
+}
+
+/// The place assigned a reference by `statement` and the region of the reference. See
+/// [infer_regions].
+fn assigned_region<'b>(
+    statement: &'b Statement,
+    regions: &HashMap<Local, Region>,
+) -> Option<(&'b Place, Region)> {
+    match &statement.kind {
+        StatementKind::Assign(place, Rvalue::Ref(region, _, borrowed)) => {
+            match (
+                borrowed.projection.split_first(),
+                regions.get(&borrowed.local),
+            ) {
+                // A reborrow through a local points to the places borrowed into it. A local may be
+                // dead by the time the reborrow is used.
+                (Some((PlaceElem::Deref, projection)), Some(Region::Concrete(places))) => {
+                    let places = places
+                        .iter()
+                        .map(|place| Place {
+                            local: place.local,
+                            projection: place
+                                .projection
+                                .iter()
+                                .chain(projection)
+                                .cloned()
+                                .collect(),
+                        })
+                        .collect();
+                    Some((place, Region::Concrete(places)))
+                }
+                (Some((PlaceElem::Deref, _)), None) => None,
+                _ => Some((place, region.clone())),
+            }
+        }
+        StatementKind::Assign(
+            place,
+            Rvalue::Use(Operand::Copy(op_place) | Operand::Move(op_place)),
+        ) if op_place.projection.is_empty() => regions
+            .get(&op_place.local)
+            .map(|region| (place, region.clone())),
+        _ => None,
+    }
+}
+
+/// The destination of a call returning a reference and the region of the reference, i.e., the
+/// regions of the arguments instantiating the universal region of the output. See
+/// [infer_regions].
+fn returned_region<'b, 'tcx>(
+    terminator: &'b Terminator<'tcx>,
+    regions: &HashMap<Local, Region>,
+    global_env: &GlobalEnv<'tcx>,
+    tcx: &TyCtxt,
+) -> Option<(&'b Place, Region)> {
+    let (def_id, substs, args, place) = match &terminator.kind {
+        TerminatorKind::Call {
+            func: (def_id, substs),
+            args,
+            destination: Some((place, _)),
+        } => (def_id, substs, args, place),
+        _ => return None,
+    };
+    let fn_sig = global_env.fn_sig(*def_id, substs, tcx);
+    let ur = match fn_sig.output_ty().kind() {
+        TyKind::Ref(_, Region::Abstract(ur), _) => ur,
+        _ => return None,
+    };
+    args.iter()
+        .zip(&fn_sig.inputs)
+        .filter_map(|(arg, gv)| match (arg, fn_sig.requires[gv].kind()) {
+            (
+                Operand::Copy(arg_place) | Operand::Move(arg_place),
+                TyKind::Ref(_, Region::Abstract(arg_ur), _),
+            ) if arg_ur == ur && arg_place.projection.is_empty() => {
+                // Only reference arguments point to the memory of the caller, which they are
+                // reborrowed from when passed on.
+                regions.get(&arg_place.local).map(|region| match region {
+                    Region::Abstract(_) => Region::from(deref(arg_place.local)),
+                    _ => region.clone(),
+                })
+            }
+            _ => None,
+        })
+        .fold1(|region1, region2| region1.join(&region2))
+        .map(|region| (place, region))
+}
+
 pub struct CheckingTask<'tcx, 'a> {
     global_env: &'a GlobalEnv<'tcx>,
     body: &'a Body<'tcx>,
     fn_decl: &'a FnSig,
     move_data: MoveData<'tcx>,
     flow_uninit: dataflow::Results<'tcx, MaybeUninitializedPlaces<'a, 'tcx>>,
+    /// The places the references in each local may point to at the start of every basic block,
+    /// as computed by the borrow checker. `None` if the borrow checker didn't check this body.
+    regions: Option<IndexVec<BasicBlock, HashMap<Local, Region>>>,
 }
 
 impl<'tcx, 'a> CheckingTask<'tcx, 'a> {
@@ -379,6 +879,7 @@ impl<'tcx, 'a> CheckingTask<'tcx, 'a> {
         fn_decl: &'a FnSig,
         move_data: MoveData<'tcx>,
         flow_uninit: dataflow::Results<'tcx, MaybeUninitializedPlaces<'a, 'tcx>>,
+        regions: Option<IndexVec<BasicBlock, HashMap<Local, Region>>>,
     ) -> Self {
         Self {
             global_env,
@@ -386,6 +887,7 @@ impl<'tcx, 'a> CheckingTask<'tcx, 'a> {
             fn_decl,
             move_data,
             flow_uninit,
+            regions,
         }
     }
 
@@ -393,17 +895,24 @@ impl<'tcx, 'a> CheckingTask<'tcx, 'a> {
         &'b self,
         tcx: &'b TyCtxt,
         kvid_gen: &'b IndexGen<KVid>,
+        ghost_gen: &'b IndexGen<GhostVar>,
+        regions: &'b HashMap<Local, Region>,
         bb: mir::BasicBlock,
-    ) -> Refiner {
+    ) -> Refiner<'b, 'tcx> {
         Refiner::new(
             tcx,
+            self.global_env.tcx(),
             &self.move_data,
             self.flow_uninit.entry_set_for_block(bb),
             kvid_gen,
+            ghost_gen,
+            regions,
         )
     }
 }
 
 pub struct CheckingResult {
     pub ok: bool,
+    /// The runtime checks that could not be proven to pass, in panic-freedom mode.
+    pub failed_asserts: Vec<(Span, AssertKind)>,
 }
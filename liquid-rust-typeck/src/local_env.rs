@@ -1,9 +1,12 @@
 use std::{collections::HashMap, fmt};
 
-use liquid_rust_common::index::IndexGen;
+use liquid_rust_common::{index::IndexGen, ordered_map::OrderedMap};
 use liquid_rust_lrir::{
     mir::{Local, PlaceElem, PlaceRef},
-    ty::{subst::Subst, FnSig, GhostVar, GhostVarMap, Path, Pred, Refine, Ty, TyCtxt, TyKind, Var},
+    ty::{
+        subst::Subst, FnSig, GhostVar, GhostVarMap, Path, Pred, Refine, Region, Ty, TyCtxt, TyKind,
+        Var,
+    },
 };
 
 use crate::{bblock_env::BBlockEnv, binding_tree::BindingTree};
@@ -48,9 +51,12 @@ impl<'tcx> LocalEnv<'tcx> {
         let mut ty = self.lookup_var(self.lookup_local(place.local));
         for elem in place.projection {
             match (ty.kind(), elem) {
-                (TyKind::Tuple(tuple), &PlaceElem::Field(n)) => {
+                (TyKind::Tuple(tuple) | TyKind::Adt(_, tuple), &PlaceElem::Field(n)) => {
                     ty = tuple.ty_at(n);
                 }
+                (TyKind::Enum(_, _, variants), &PlaceElem::Downcast(variant)) => {
+                    ty = &variants[variant];
+                }
                 (TyKind::Ref(.., gv), PlaceElem::Deref) => {
                     ty = self.lookup_var(gv);
                 }
@@ -96,17 +102,102 @@ impl<'tcx> LocalEnv<'tcx> {
                 let ty = self.update_rec(tup.ty_at(n), &projs[1..], ty);
                 tcx.mk_tuple(tup.map_ty_at(n, |_| ty))
             }
+            (TyKind::Adt(adt_def, tup), &[PlaceElem::Field(n), ..]) => {
+                let ty = self.update_rec(tup.ty_at(n), &projs[1..], ty);
+                tcx.mk_adt(adt_def.clone(), tup.map_ty_at(n, |_| ty))
+            }
+            (TyKind::Enum(enum_def, known, variants), &[PlaceElem::Downcast(variant), ..]) => {
+                let mut variants = variants.clone();
+                variants[variant] = self.update_rec(&variants[variant], &projs[1..], ty);
+                tcx.mk_enum(enum_def.clone(), *known, variants)
+            }
             (TyKind::Ref(bk, r, gv), [PlaceElem::Deref, ..]) => {
                 let root = tcx.selfify(self.lookup_var(gv), Path::from(*gv));
                 let fresh_gv = self.fresh_ghost();
                 let ty = self.update_rec(&root, &projs[1..], ty);
                 self.push_binding(fresh_gv, ty);
+                self.update_borrowed(r, fresh_gv);
                 tcx.mk_ref(*bk, r.clone(), fresh_gv)
             }
             _ => unreachable!("{} {:?}", root, projs),
         }
     }
 
+    /// Update the places borrowed by a mutable reference in `region` after its pointee was updated
+    /// to `pointee`. The places aren't accessible while they are borrowed, so they can be updated
+    /// right away and they hold the new value when the borrow ends.
+    fn update_borrowed(&mut self, region: &Region, pointee: GhostVar) {
+        match region {
+            Region::Concrete(places) => match places.as_slice() {
+                [] => {}
+                [place] => {
+                    let ty = self
+                        .tcx
+                        .selfify(self.lookup_var(pointee), Path::from(pointee));
+                    self.update(place.as_ref(), ty);
+                }
+                // Only one of the places is written, but we don't know which one, so each of them
+                // either keeps its value or gets the new one.
+                places => {
+                    for place in places {
+                        let ty = self.tcx.selfify_either(
+                            &self.lookup(place.as_ref()),
+                            self.current_path(place.as_ref()),
+                            Path::from(pointee),
+                        );
+                        self.update(place.as_ref(), ty);
+                    }
+                }
+            },
+            // The reference points to memory owned by a caller.
+            Region::Abstract(_) => {}
+            Region::Infer(_) => unreachable!("regions are inferred before checking"),
+        }
+    }
+
+    /// Narrow the enum at `place` to `variant`, binding the fields of the variant.
+    pub fn downcast(&mut self, place: PlaceRef, variant: usize) {
+        let ty = self.lookup(place);
+        match ty.kind() {
+            TyKind::Enum(_, Some(known), _) if *known == variant => {}
+            TyKind::Enum(enum_def, None, variants) => {
+                let ty = self
+                    .tcx
+                    .mk_enum(enum_def.clone(), Some(variant), variants.clone());
+                self.update(place, ty);
+            }
+            _ => unreachable!("{} can't be variant {}", ty, variant),
+        }
+    }
+
+    /// Narrow the enums `place` projects into a variant of to that variant. A field of a variant
+    /// can only be read while the enum is that variant.
+    pub fn downcast_projections(&mut self, place: PlaceRef) {
+        for (i, elem) in place.projection.iter().enumerate() {
+            if let PlaceElem::Downcast(variant) = elem {
+                let base = PlaceRef {
+                    local: place.local,
+                    projection: &place.projection[..i],
+                };
+                self.downcast(base, *variant);
+            }
+        }
+    }
+
+    /// Make the enum at `place` the variant `variant`, whose fields were already written.
+    pub fn set_discriminant(&mut self, place: PlaceRef, variant: usize) {
+        let ty = self.lookup(place);
+        match ty.kind() {
+            TyKind::Enum(enum_def, _, variants) => {
+                let ty = self
+                    .tcx
+                    .mk_enum(enum_def.clone(), Some(variant), variants.clone());
+                self.update(place, ty);
+            }
+            _ => unreachable!("{} is not an enum", ty),
+        }
+    }
+
     /// "Borrow" `place` copying a selfified version of its type and assigning it a fresh [GhostVar].
     pub fn borrow(&mut self, place: PlaceRef) -> GhostVar {
         let ty = self
@@ -124,10 +215,14 @@ impl<'tcx> LocalEnv<'tcx> {
         let mut projs = Vec::new();
         for proj in place.projection {
             match (ty.kind(), proj) {
-                (TyKind::Tuple(tup), &PlaceElem::Field(n)) => {
+                (TyKind::Tuple(tup) | TyKind::Adt(_, tup), &PlaceElem::Field(n)) => {
                     ty = tup.ty_at(n);
                     projs.push(n);
                 }
+                (TyKind::Enum(_, _, variants), &PlaceElem::Downcast(variant)) => {
+                    ty = &variants[variant];
+                    projs.push(variant);
+                }
                 (TyKind::Ref(.., gv), PlaceElem::Deref) => {
                     projs.clear();
                     base = *gv;
@@ -139,11 +234,15 @@ impl<'tcx> LocalEnv<'tcx> {
         Path::new(base.into(), projs)
     }
 
+    /// Instantiate `fn_sig` for a call with arguments `args`. Returns the environment the
+    /// arguments must satisfy, the bindings ensured by the callee, the type of the output and the
+    /// updated type of the pointee of each mutable reference argument.
+    #[allow(clippy::type_complexity)]
     pub fn open_fn_sig(
         &self,
         fn_sig: &FnSig,
         args: &[Local],
-    ) -> (BBlockEnv, Vec<(GhostVar, Ty)>, Ty) {
+    ) -> (BBlockEnv, Vec<(GhostVar, Ty)>, Ty, Vec<(Local, Ty)>) {
         let tcx = self.tcx;
         let mut subst = self.infer_call_subst(fn_sig, args);
         let input_env = BBlockEnv {
@@ -156,16 +255,19 @@ impl<'tcx> LocalEnv<'tcx> {
         };
         let mut ret = None;
         let mut out_env = vec![];
+        let mut updated = vec![];
         for (gv, ty) in &fn_sig.ensures {
             let fresh_gv = self.ghost_gen.fresh();
             let ty = subst.apply(ty, tcx);
             out_env.push((fresh_gv, ty.clone()));
             if *gv == fn_sig.output {
                 ret = Some(ty)
+            } else if let Some((i, _)) = fn_sig.outputs.iter().find(|(_, output)| output == gv) {
+                updated.push((args[*i], tcx.selfify(&ty, Path::from(fresh_gv))));
             }
             subst.add_ghost_var_subst(*gv, fresh_gv);
         }
-        (input_env, out_env, ret.unwrap())
+        (input_env, out_env, ret.unwrap(), updated)
     }
 
     pub fn infer_call_subst(&self, fn_sig: &FnSig, args: &[Local]) -> Subst {
@@ -176,6 +278,13 @@ impl<'tcx> LocalEnv<'tcx> {
             let ty1 = self.lookup_var(&gv1);
             let ty2 = &fn_sig.requires[&gv2];
             subst.infer(self, ty1, &fn_sig.requires, ty2);
+            // The universal regions of the callee are instantiated with the regions of the
+            // references passed for them.
+            if let (TyKind::Ref(_, r1, _), TyKind::Ref(_, Region::Abstract(ur), _)) =
+                (ty1.kind(), ty2.kind())
+            {
+                subst.add_region_subst(*ur, r1);
+            }
         }
         subst
     }
@@ -198,31 +307,95 @@ impl<'tcx> LocalEnv<'tcx> {
                 .tcx
                 .selfify(&self.lookup(PlaceRef::from(local)), Path::from(gv));
             let ty2 = &env.ghost_vars[&gv];
-            self.subtyping(ty1, ty2);
+            self.subtyping(ty1, ty2, env);
         }
     }
 
-    pub fn subtyping(&mut self, ty1: &Ty, ty2: &Ty) {
+    /// Check that `ty1` is a subtype of `ty2`, where the pointees of the references in `ty2` are
+    /// bound in `env`.
+    pub fn subtyping(&mut self, ty1: &Ty, ty2: &Ty, env: &BBlockEnv) {
         let depth = self.bindings.curr_depth();
         match (ty1.kind(), ty2.kind()) {
-            (TyKind::Tuple(tup1), TyKind::Tuple(tup2)) if tup1.len() == tup2.len() => {
-                for ((fld, ty1), ty2) in tup1.iter().zip(tup2.types()) {
-                    self.subtyping(ty1, ty2);
-                    self.push_binding(*fld, ty1.clone());
+            (TyKind::Tuple(tup1), TyKind::Tuple(tup2))
+            | (TyKind::Adt(_, tup1), TyKind::Adt(_, tup2))
+                if tup1.len() == tup2.len() =>
+            {
+                for ((fld1, ty1), (fld2, ty2)) in tup1.iter().zip(tup2.iter()) {
+                    self.subtyping(ty1, ty2, env);
+                    // The following fields of either tuple may refer to this one.
+                    self.push_binding(*fld1, ty1.clone());
+                    if fld1 != fld2 {
+                        let ty = self.tcx.selfify(ty1, Path::from(*fld1));
+                        self.push_binding(*fld2, ty);
+                    }
+                }
+            }
+            (TyKind::Enum(_, variant1, variants1), TyKind::Enum(_, variant2, variants2)) => {
+                match (variant1, variant2) {
+                    // Only the fields of the variant the value is known to be matter.
+                    (Some(i), None) => self.subtyping(&variants1[*i], &variants2[*i], env),
+                    (Some(i), Some(j)) if i == j => {
+                        self.subtyping(&variants1[*i], &variants2[*i], env)
+                    }
+                    (None, None) => {
+                        for (ty1, ty2) in variants1.iter().zip(variants2) {
+                            self.subtyping(ty1, ty2, env);
+                        }
+                    }
+                    _ => unreachable!("{} {}", ty1, ty2),
                 }
             }
             (TyKind::Refined(bty1, _), TyKind::Refined(bty2, refine2)) if bty1 == bty2 => {
                 self.bindings.push_binding(Var::Nu, ty1.clone());
                 self.bindings.push_pred(refine2.clone());
             }
-            (TyKind::Refined(..) | TyKind::Uninit(..), TyKind::Uninit(size))
+            (TyKind::Refined(..) | TyKind::Ref(..) | TyKind::Uninit(..), TyKind::Uninit(size))
                 if ty1.size() == *size => {}
-            (TyKind::Ref(..), TyKind::Ref(..)) => todo!(),
+            // FIXME: check that the region of `ty1` is contained in the region of `ty2`.
+            (TyKind::Ref(bk1, _, gv1), TyKind::Ref(bk2, _, gv2)) if bk1 == bk2 => {
+                let ty1 = self.tcx.selfify(self.lookup_var(gv1), Path::from(*gv1));
+                self.subtyping(&ty1, &env.ghost_vars[gv2], env);
+            }
             _ => unreachable!("{} {}", ty1, ty2),
         }
         self.bindings.pop_to(depth);
     }
 
+    /// Check that the struct `ty` satisfies its invariant.
+    pub fn check_invariant(&mut self, ty: &Ty) {
+        if let TyKind::Adt(adt_def, _) = ty.kind() {
+            let empty_env = BBlockEnv {
+                ghost_vars: OrderedMap::new(),
+                locals: vec![],
+            };
+            let invariant = self.tcx.mk_adt_invariant(adt_def);
+            self.subtyping(ty, &invariant, &empty_env);
+        }
+    }
+
+    /// Check that the structs `place` is a field of satisfy their invariant after a write to
+    /// `place`. The invariant of a struct is only checked once all its fields are initialized.
+    pub fn check_invariants(&mut self, place: PlaceRef) {
+        for (i, elem) in place.projection.iter().enumerate() {
+            let base = PlaceRef {
+                local: place.local,
+                projection: &place.projection[..i],
+            };
+            let ty = self.lookup(base);
+            match (ty.kind(), elem) {
+                (TyKind::Adt(_, tup), PlaceElem::Field(_))
+                    if tup
+                        .types()
+                        .all(|ty| !matches!(ty.kind(), TyKind::Uninit(_))) =>
+                {
+                    let ty = self.tcx.selfify(&ty, self.current_path(base));
+                    self.check_invariant(&ty);
+                }
+                _ => {}
+            }
+        }
+    }
+
     pub fn enter_basic_block(&mut self, bb_env: &BBlockEnv, f: impl FnOnce(&mut Self)) {
         let depth = self.bindings.curr_depth();
         let mut subst = Subst::new();
@@ -243,10 +416,28 @@ impl<'tcx> LocalEnv<'tcx> {
         self.bindings.pop_to(depth);
     }
 
+    /// Check `f` assuming `guard`. The places updated by `f`, e.g., an enum narrowed to a
+    /// variant, go back to their previous types afterwards.
     pub fn with_guard(&mut self, guard: Pred, f: impl FnOnce(&mut Self)) {
         let depth = self.bindings.curr_depth();
+        self.locals.push(self.locals.last().unwrap().clone());
         self.bindings.push_guard(Refine::Pred(guard));
         f(self);
+        self.locals.pop();
+        self.bindings.pop_to(depth);
+    }
+
+    /// Require `pred` to hold in the current environment, reporting it by `tag` if it doesn't.
+    pub fn assert(&mut self, pred: Pred, tag: String) {
+        let depth = self.bindings.curr_depth();
+        self.bindings.push_tagged_pred(Refine::Pred(pred), tag);
+        self.bindings.pop_to(depth);
+    }
+
+    /// Require `pred` to hold in the current environment.
+    pub fn require(&mut self, pred: Pred) {
+        let depth = self.bindings.curr_depth();
+        self.bindings.push_pred(Refine::Pred(pred));
         self.bindings.pop_to(depth);
     }
 
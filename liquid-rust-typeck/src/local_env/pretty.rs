@@ -56,6 +56,32 @@ fn write_ty(l: &LocalEnv, w: &mut dyn Write, t: &TyS) -> io::Result<()> {
             }
             write!(w, ")")
         }
+        TyKind::Adt(adt_def, tup) => {
+            write!(w, "{} {{ ", adt_def.name)?;
+            for (i, (name, ty)) in adt_def.field_names.iter().zip(tup.types()).enumerate() {
+                if i != 0 {
+                    write!(w, ", ")?;
+                }
+                write!(w, "{}: ", name)?;
+                write_ty(l, w, ty)?;
+            }
+            write!(w, " }}")
+        }
+        TyKind::Enum(enum_def, Some(variant), variants) => {
+            write!(w, "{}::{}", enum_def.name, enum_def.variants[*variant].name)?;
+            write_ty(l, w, &variants[*variant])
+        }
+        TyKind::Enum(enum_def, None, variants) => {
+            write!(w, "{}::{{", enum_def.name)?;
+            for (i, (variant_def, ty)) in enum_def.variants.iter().zip(variants).enumerate() {
+                if i != 0 {
+                    write!(w, " | ")?;
+                }
+                write!(w, "{}", variant_def.name)?;
+                write_ty(l, w, ty)?;
+            }
+            write!(w, "}}")
+        }
         TyKind::Uninit(size) => write!(w, "uninit({})", size),
         TyKind::Refined(bty, Refine::Infer(k)) => write!(w, "{{ {} | {} }}", bty, k),
         TyKind::Refined(bty, Refine::Pred(pred)) => {
@@ -0,0 +1,116 @@
+//! `cargo liquid`: check every member of a cargo workspace with Liquid Rust.
+//!
+//! The workspace is checked with `cargo check`, using `liquid-rust` as `RUSTC_WORKSPACE_WRAPPER`
+//! so that only workspace members are verified while their dependencies are compiled by plain
+//! rustc. Each member writes its verification results to a directory inside the target directory,
+//! which are summarized once cargo finishes. A member that fails verification is still compiled,
+//! so the rest of the workspace is checked too, and the exit code is set from the results. Members
+//! that cargo doesn't rebuild because they are up to date are not checked again, so they are not
+//! in the summary.
+
+use liquid_rust_common::results::CrateResults;
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::{exit, Command},
+};
+
+/// Usage: `cargo liquid [<cargo check options>]`.
+fn main() {
+    // Cargo calls us as `cargo-liquid liquid <args>`.
+    let args: Vec<String> = env::args().skip(2).collect();
+    let cargo = env::var("CARGO").unwrap_or_else(|_| "cargo".into());
+
+    // Results of earlier runs are cleared, so that only the crates checked by this run are
+    // summarized.
+    let results_dir = target_dir(&cargo).join("liquid");
+    if results_dir.exists() {
+        fs::remove_dir_all(&results_dir).expect("could not clear the results directory");
+    }
+    fs::create_dir_all(&results_dir).expect("could not create the results directory");
+
+    let status = Command::new(&cargo)
+        .arg("check")
+        .args(&args)
+        .env("RUSTC_WORKSPACE_WRAPPER", liquid_rust())
+        .env("LR_RESULTS_DIR", &results_dir)
+        .status()
+        .expect("could not run cargo");
+
+    let verified = print_summary(&results_dir);
+
+    if !verified {
+        exit(1)
+    }
+    exit(status.code().unwrap_or(1))
+}
+
+/// The path to the `liquid-rust` binary, which is installed next to this one.
+fn liquid_rust() -> PathBuf {
+    let current_exe = env::current_exe().expect("could not find the cargo-liquid binary");
+    current_exe.with_file_name(format!("liquid-rust{}", env::consts::EXE_SUFFIX))
+}
+
+/// The target directory of the workspace in the current directory.
+fn target_dir(cargo: &str) -> PathBuf {
+    let output = Command::new(cargo)
+        .args(&["metadata", "--no-deps", "--format-version", "1"])
+        .output()
+        .expect("could not run cargo metadata");
+    if !output.status.success() {
+        eprint!("{}", String::from_utf8_lossy(&output.stderr));
+        exit(1)
+    }
+
+    let metadata: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("invalid cargo metadata");
+    metadata["target_directory"]
+        .as_str()
+        .expect("invalid cargo metadata")
+        .into()
+}
+
+/// Print the verification results of every crate in `results_dir` and return whether all of
+/// them were verified.
+fn print_summary(results_dir: &Path) -> bool {
+    let mut entries = fs::read_dir(results_dir)
+        .expect("could not read the results directory")
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .collect::<Vec<_>>();
+    entries.sort();
+
+    let mut verified = true;
+    println!("Liquid Rust summary:");
+
+    for path in entries {
+        // Results files are named after the crate and its type, e.g., `my_crate.rlib`.
+        let name = match (path.file_stem(), path.extension()) {
+            (Some(name), Some(crate_type)) => format!(
+                "{} ({})",
+                name.to_string_lossy(),
+                crate_type.to_string_lossy()
+            ),
+            _ => continue,
+        };
+
+        match CrateResults::read(&path) {
+            Ok(results) => {
+                if results.is_ok() {
+                    println!("    {}: {} checked, ok", name, results.checked);
+                } else {
+                    verified = false;
+                    println!(
+                        "    {}: {} checked, {} failed, {} errors",
+                        name, results.checked, results.failed, results.errors
+                    );
+                }
+                for skipped in &results.skipped {
+                    println!("        skipped {}", skipped);
+                }
+            }
+            Err(err) => println!("    {}: {}", name, err),
+        }
+    }
+
+    verified
+}
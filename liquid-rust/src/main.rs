@@ -1,5 +1,10 @@
-use liquid_rust_common::config::CMD_PREFIX;
-use std::{env::args, process::exit};
+use liquid_rust_common::config::{self, Codegen, MirPhase};
+use liquid_rust_driver::EXIT_FAILURE;
+use std::{
+    env::args,
+    path::Path,
+    process::{exit, Command},
+};
 
 /// Get the path to the sysroot of the current rustup toolchain. Return `None` if the rustup
 /// environment variables are not set.
@@ -9,18 +14,92 @@ fn sysroot() -> Option<String> {
     Some(format!("{}/toolchains/{}", home, toolchain))
 }
 
-fn main() {
-    // Get the arguments from the command line.
-    let mut args: Vec<String> = args().filter(|x| !x.starts_with(CMD_PREFIX)).collect();
+/// Whether the compiler invocation described by `args` should be checked by Liquid Rust when
+/// running as a cargo wrapper. Build scripts and queries made by cargo, e.g., `rustc -vV`, are not.
+fn should_check(args: &[String]) -> bool {
+    let has_input = args.iter().any(|arg| arg.ends_with(".rs"));
+    let is_query = args.iter().any(|arg| arg.starts_with("--print"));
+    let is_build_script = args
+        .windows(2)
+        .any(|w| w[0] == "--crate-name" && w[1] == "build_script_build");
+
+    has_input && !is_query && !is_build_script
+}
+
+/// Remove the optimization level set by `args`, if any.
+fn remove_opt_level(args: &mut Vec<String>) {
+    let mut i = 0;
+    while i < args.len() {
+        if args[i] == "-C"
+            && args
+                .get(i + 1)
+                .map_or(false, |arg| arg.starts_with("opt-level="))
+        {
+            args.drain(i..i + 2);
+        } else if args[i].starts_with("-Copt-level=") {
+            args.remove(i);
+        } else {
+            i += 1;
+        }
+    }
+}
+
+/// Run the rust compiler at `rustc` with the arguments `args`.
+fn run_rustc(rustc: &str, args: &[String]) -> i32 {
+    let status = Command::new(rustc)
+        .args(args)
+        .status()
+        .expect("could not run rustc");
+    status.code().unwrap_or(1)
+}
+
+fn run_liquid_rust(args: &[String]) -> i32 {
+    // Liquid Rust settings are left in the arguments, the driver takes them out.
+    let mut args = args.to_vec();
     // Add the sysroot path to the arguments.
     args.push("--sysroot".into());
     args.push(sysroot().expect("Liquid Rust requires rustup to be built."));
-    // Record nll-facts for this compilation.
-    args.push("-Znll-facts".into());
-    // Add release mode to the arguments.
-    args.push("-O".into());
+    // Optimized MIR is verified in release mode, unless code is generated, in which case the
+    // artifacts must be the ones rustc would produce with the same arguments. Other phases are not
+    // affected by optimizations, so the optimization level and overflow checks are left as
+    // requested.
+    if config::mir_phase() == MirPhase::Optimized && config::codegen() == Codegen::Never {
+        remove_opt_level(&mut args);
+        args.push("-O".into());
+    }
     // Run the rust compiler with the arguments.
-    let exit_code = liquid_rust_driver::run_compiler(args);
+    liquid_rust_driver::run_compiler(args)
+}
+
+fn main() {
+    // Get the arguments from the command line.
+    let mut args: Vec<String> = args().collect();
+
+    // When used as `RUSTC_WORKSPACE_WRAPPER`, e.g., by `cargo liquid`, cargo calls us with the path
+    // to rustc followed by its arguments. Workspace members are checked first and then compiled by
+    // rustc as usual. A source file named `rustc.rs` is an input, not a compiler.
+    let is_wrapper = args.get(1).map_or(false, |arg| {
+        let path = Path::new(arg);
+        path.file_stem() == Some("rustc".as_ref()) && path.extension() != Some("rs".as_ref())
+    });
+    if is_wrapper {
+        let rustc = args.remove(1);
+        let rustc_args = &args[1..];
+
+        if should_check(rustc_args) {
+            let exit_code = run_liquid_rust(&args);
+            // A verification failure is recorded in the results directory, if any, and reported
+            // by `cargo liquid` once the whole workspace is checked. Stopping here would make
+            // cargo skip the remaining members. A crash still stops the build.
+            let is_recorded = exit_code == EXIT_FAILURE && config::results_dir().is_some();
+            if exit_code != 0 && !is_recorded {
+                exit(exit_code)
+            }
+        }
+        exit(run_rustc(&rustc, rustc_args))
+    }
+
+    let exit_code = run_liquid_rust(&args);
     // Exit with the exit code returned by the compiler.
     exit(exit_code)
 }